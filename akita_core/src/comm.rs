@@ -148,4 +148,156 @@ pub fn keywords_safe(s: &str) -> String {
     } else {
         s.to_string()
     }
-}
\ No newline at end of file
+}
+/// Break the sql into lines before its major clauses for logging, quoted text is left untouched
+/// and only the whitespace ahead of a clause is replaced, so the statement means the same.
+pub fn format_sql(sql: &str) -> String {
+    const CLAUSES: [&str; 10] = ["select", "from", "where", "group by", "having", "order by", "limit", "values", "set", "union"];
+    let chars = sql.trim().chars().collect::<Vec<char>>();
+    let mut formatted = String::with_capacity(sql.len() + 16);
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(q) => {
+                if c == '\\' && i + 1 < chars.len() {
+                    formatted.push(c);
+                    i += 1;
+                    formatted.push(chars[i]);
+                    i += 1;
+                    continue;
+                }
+                if c == q { quote = None; }
+            }
+            None if c == '\'' || c == '"' || c == '`' => quote = Some(c),
+            None if i > 0 && chars[i - 1].is_whitespace() => {
+                let rest = chars[i..].iter().take(9).collect::<String>().to_lowercase();
+                let clause = CLAUSES.iter().find(|clause| rest.starts_with(*clause)
+                    && chars.get(i + clause.len()).map(|n| n.is_whitespace() || *n == '(').unwrap_or(true));
+                if clause.is_some() {
+                    while formatted.ends_with(char::is_whitespace) { formatted.pop(); }
+                    formatted.push('\n');
+                }
+            }
+            None => {}
+        }
+        formatted.push(c);
+        i += 1;
+    }
+    formatted
+}
+
+/// Strip the literals of the sql, so queries differing only in their values share one shape.
+pub fn normalize_sql(sql: &str) -> String {
+    let mut shape = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                while let Some(n) = chars.next() {
+                    if n == '\\' { chars.next(); } else if n == c { break; }
+                }
+                shape.push('?');
+            }
+            '0'..='9' if !shape.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '$') => {
+                while chars.peek().map(|n| n.is_ascii_digit() || *n == '.').unwrap_or(false) { chars.next(); }
+                shape.push('?');
+            }
+            c if c.is_whitespace() => {
+                if !shape.ends_with(' ') { shape.push(' '); }
+            }
+            _ => shape.push(c),
+        }
+    }
+    shape.trim().to_string()
+}
+
+/// Split a sql script into its statements. Delimiters inside quotes and comments are skipped,
+/// and the `DELIMITER` directive of the MySQL client changes the delimiter for stored routines.
+/// Both `--`/`#` line comments and `/* */` block comments are recognized, comment-only statements are dropped.
+pub fn split_sql_statements(script: &str) -> Vec<String> {
+    let chars = script.chars().collect::<Vec<char>>();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut has_code = false;
+    let mut delimiter = vec![';'];
+    let mut line_start = true;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if line_start {
+            line_start = false;
+            let line = chars[i..].iter().take_while(|c| **c != '\n').collect::<String>();
+            let directive = line.trim_start();
+            if directive.get(..10).map(|d| d.eq_ignore_ascii_case("delimiter ")).unwrap_or(false) {
+                if let Some(d) = directive[10..].split_whitespace().next() {
+                    delimiter = d.chars().collect();
+                }
+                i += line.chars().count() + 1;
+                line_start = true;
+                continue;
+            }
+        }
+        let end = match c {
+            '\'' | '"' | '`' => {
+                let mut j = i + 1;
+                while j < chars.len() && chars[j] != c {
+                    j += if chars[j] == '\\' { 2 } else { 1 };
+                }
+                has_code = true;
+                (j + 1).min(chars.len())
+            }
+            '#' => i + chars[i..].iter().take_while(|c| **c != '\n').count(),
+            '-' if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).map(|n| n.is_whitespace()).unwrap_or(true) => {
+                i + chars[i..].iter().take_while(|c| **c != '\n').count()
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let mut j = i + 2;
+                while j < chars.len() && !(chars[j] == '*' && chars.get(j + 1) == Some(&'/')) { j += 1; }
+                (j + 2).min(chars.len())
+            }
+            _ if chars[i..].starts_with(&delimiter) => {
+                if has_code {
+                    statements.push(current.trim().to_string());
+                }
+                current.clear();
+                has_code = false;
+                i += delimiter.len();
+                continue;
+            }
+            _ => {
+                if c == '\n' { line_start = true; }
+                if !c.is_whitespace() { has_code = true; }
+                i + 1
+            }
+        };
+        current.extend(&chars[i..end]);
+        i = end;
+    }
+    if has_code {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
+#[cfg(test)]
+mod test {
+    use crate::comm::{format_sql, split_sql_statements};
+
+    #[test]
+    fn format_sql_clauses() {
+        assert_eq!(format_sql("SELECT `id`, `name` FROM t_user  WHERE (name = 'a from b') ORDER BY id asc limit 0, 10"),
+            "SELECT `id`, `name`\nFROM t_user\nWHERE (name = 'a from b')\nORDER BY id asc\nlimit 0, 10");
+    }
+
+    #[test]
+    fn split_statements() {
+        let script = "-- seed; data\nINSERT INTO t VALUES ('a;b', \"c\\\";\"); /* ; */\n# done;\nDELIMITER $$\nCREATE PROCEDURE p() BEGIN SELECT 1; END$$\ndelimiter ;\nSELECT 2;\n-- trailing";
+        assert_eq!(split_sql_statements(script), vec![
+            "-- seed; data\nINSERT INTO t VALUES ('a;b', \"c\\\";\")",
+            "/* ; */\n# done;\nCREATE PROCEDURE p() BEGIN SELECT 1; END",
+            "SELECT 2",
+        ]);
+    }
+}
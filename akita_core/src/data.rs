@@ -4,10 +4,27 @@ use crate::value::Value;
 
 /// use this to store data retrieved from the database
 /// This is also slimmer than Vec<Dao> when serialized
+///
+/// Each row comes out as an object value keyed by the column names, borrowing with `iter()` or consuming the rows,
+/// both skip empty rows:
+/// ```
+/// use akita_core::{Rows, Value};
+/// let mut rows = Rows::new(vec!["id".to_string()]);
+/// rows.push(vec![Value::Int(1)]);
+/// rows.push(vec![]);
+/// rows.push(vec![Value::Int(2)]);
+/// for row in rows.iter() {
+///     assert!(row.get_obj::<i32>("id").is_ok());
+/// }
+/// let ids = rows.into_iter().map(|row| row.get_obj::<i32>("id").unwrap_or_default()).collect::<Vec<i32>>();
+/// assert_eq!(ids, vec![1, 2]);
+/// ```
 #[derive(Debug, PartialEq, Clone)]
 pub struct Rows {
     pub columns: Vec<String>,
     pub data: Vec<Vec<Value>>,
+    /// name and coarse type of each column, in the same order as `columns`
+    pub column_info: Vec<ColumnInfo>,
     /// can be optionally set, indicates how many total rows are there in the table
     pub count: Option<usize>,
 }
@@ -16,9 +33,11 @@ impl Rows {
     pub fn empty() -> Self { Rows::new(vec![]) }
 
     pub fn new(columns: Vec<String>) -> Self {
+        let column_info = columns.iter().map(|name| ColumnInfo { name: name.to_owned(), column_type: ColumnKind::Unknown }).collect();
         Rows {
             columns,
             data: vec![],
+            column_info,
             count: None,
         }
     }
@@ -35,43 +54,152 @@ impl Rows {
         Iter {
             columns: self.columns.clone(),
             iter: self.data.iter(),
+            remaining: self.data.iter().filter(|row| !row.is_empty()).count(),
         }
     }
 
     pub fn len(&self) -> usize {
         self.data.len()
     }
+
+    /// Returns the name and coarse type of each column of the result.
+    pub fn column_info(&self) -> &[ColumnInfo] {
+        &self.column_info
+    }
+
+    /// Set the column types reported by the driver, in column order.
+    pub fn set_column_kinds(&mut self, kinds: Vec<ColumnKind>) {
+        for (info, kind) in self.column_info.iter_mut().zip(kinds.into_iter()) {
+            info.column_type = kind;
+        }
+    }
+
+    /// Guess the column types from the first non-null value of each column,
+    /// for drivers without column type metadata.
+    pub fn infer_column_kinds(&mut self) {
+        for (i, info) in self.column_info.iter_mut().enumerate() {
+            if let Some(value) = self.data.iter().filter_map(|row| row.get(i)).find(|v| !v.is_nil()) {
+                info.column_type = ColumnKind::of(value);
+            }
+        }
+    }
+}
+
+/// The coarse type of a result column.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColumnKind {
+    Bool,
+    Integer,
+    Float,
+    Decimal,
+    Text,
+    Bytes,
+    Json,
+    Date,
+    Time,
+    DateTime,
+    Unknown,
+}
+
+impl ColumnKind {
+    /// The coarse type of a single value.
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) => ColumnKind::Bool,
+            Value::Tinyint(_) | Value::Smallint(_) | Value::Int(_) | Value::Bigint(_) => ColumnKind::Integer,
+            Value::Float(_) | Value::Double(_) => ColumnKind::Float,
+            Value::BigDecimal(_) => ColumnKind::Decimal,
+            Value::Char(_) | Value::Text(_) | Value::Uuid(_) => ColumnKind::Text,
+            Value::Blob(_) => ColumnKind::Bytes,
+            Value::Json(_) | Value::Object(_) | Value::Array(_) => ColumnKind::Json,
+            Value::Date(_) => ColumnKind::Date,
+            Value::Time(_) => ColumnKind::Time,
+            Value::DateTime(_) | Value::Timestamp(_) => ColumnKind::DateTime,
+            Value::Nil | Value::Interval(_) => ColumnKind::Unknown,
+        }
+    }
+}
+
+/// Column metadata of a query result.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub column_type: ColumnKind,
 }
 
 /// An iterator over `Row`s.
 pub struct Iter<'a> {
     columns: Vec<String>,
     iter: slice::Iter<'a, Vec<Value>>,
+    /// the non-empty rows left, empty rows are skipped
+    remaining: usize,
 }
 
 impl<'a> Iterator for Iter<'a> {
     type Item = Value;
 
     fn next(&mut self) -> Option<Value> {
-        let next_row = self.iter.next();
-        if let Some(row) = next_row {
-            if !row.is_empty() {
-                let mut v = Value::new_object();
-                for (i, column) in self.columns.iter().enumerate() {
-                    if let Some(value) = row.get(i) {
-                        v.insert_obj_value(column, value);
-                    }
+        loop {
+            let row = self.iter.next()?;
+            if row.is_empty() {
+                continue;
+            }
+            let mut v = Value::new_object();
+            for (i, column) in self.columns.iter().enumerate() {
+                if let Some(value) = row.get(i) {
+                    v.insert_obj_value(column, value);
                 }
-                Some(v)
-            } else {
-                None
             }
-        } else {
-            None
+            self.remaining -= 1;
+            return Some(v);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { (self.remaining, Some(self.remaining)) }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+impl IntoIterator for Rows {
+    type Item = Value;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            columns: self.columns,
+            remaining: self.data.iter().filter(|row| !row.is_empty()).count(),
+            iter: self.data.into_iter(),
+        }
+    }
+}
+
+/// An owning iterator over `Row`s.
+pub struct IntoIter {
+    columns: Vec<String>,
+    iter: std::vec::IntoIter<Vec<Value>>,
+    /// the non-empty rows left, empty rows are skipped
+    remaining: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        loop {
+            let row = self.iter.next()?;
+            if row.is_empty() {
+                continue;
+            }
+            let mut v = Value::new_object();
+            for (column, value) in self.columns.iter().zip(row.into_iter()) {
+                v.insert_obj_value(column, &value);
+            }
+            self.remaining -= 1;
+            return Some(v);
         }
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
+    fn size_hint(&self) -> (usize, Option<usize>) { (self.remaining, Some(self.remaining)) }
 }
 
-impl<'a> ExactSizeIterator for Iter<'a> {}
\ No newline at end of file
+impl ExactSizeIterator for IntoIter {}
@@ -1,10 +1,12 @@
-use std::fmt;
+use std::{fmt, num::{ParseFloatError, ParseIntError}};
 
 
 
 #[derive(Debug)]
 pub enum ConvertError {
     NotSupported(String, String),
+    /// the text which failed to parse and the parse error
+    Parse(String, String),
 }
 
 impl fmt::Display for ConvertError {
@@ -23,6 +25,48 @@ impl From<serde_json::Error> for ConvertError {
     }
 }
 
+impl From<ParseIntError> for ConvertError {
+    fn from(err: ParseIntError) -> Self {
+        ConvertError::Parse(String::default(), err.to_string())
+    }
+}
+
+impl From<ParseFloatError> for ConvertError {
+    fn from(err: ParseFloatError) -> Self {
+        ConvertError::Parse(String::default(), err.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for ConvertError {
+    fn from(err: chrono::ParseError) -> Self {
+        ConvertError::Parse(String::default(), err.to_string())
+    }
+}
+
+impl From<ConvertError> for AkitaDataError {
+    fn from(err: ConvertError) -> Self {
+        AkitaDataError::ConvertError(err)
+    }
+}
+
+impl From<ParseIntError> for AkitaDataError {
+    fn from(err: ParseIntError) -> Self {
+        AkitaDataError::ConvertError(err.into())
+    }
+}
+
+impl From<ParseFloatError> for AkitaDataError {
+    fn from(err: ParseFloatError) -> Self {
+        AkitaDataError::ConvertError(err.into())
+    }
+}
+
+impl From<chrono::ParseError> for AkitaDataError {
+    fn from(err: chrono::ParseError) -> Self {
+        AkitaDataError::ConvertError(err.into())
+    }
+}
+
 impl From<serde_json::Error> for AkitaDataError {
     fn from(err: serde_json::Error) -> Self {
         AkitaDataError::ConvertError(ConvertError::NotSupported(err.to_string(), "SerdeJson".to_string()))
@@ -1,4 +1,4 @@
-use std::hash::{Hasher, Hash};
+use std::{borrow::Cow, hash::{Hasher, Hash}};
 
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
@@ -15,6 +15,22 @@ pub trait GetTableName {
 pub trait GetFields {
     /// extract the columns from struct
     fn fields() -> Vec<FieldName>;
+
+    /// the escaped select list of the columns which exist in the table and should be selected
+    fn select_columns() -> Cow<'static, str> {
+        Cow::Owned(Self::fields()
+            .iter().filter(|f| f.exist && f.select)
+            .map(|c| format!("`{}`", c.name))
+            .collect::<Vec<_>>()
+            .join(", "))
+    }
+}
+
+/// A hook run on an entity right after it's mapped from a row, to fill in the derived fields that don't exist
+/// in the table, e.g. `is_org` from `org_id`. Enabled with `#[table(after_load)]` on an `AkitaTable` struct,
+/// it's then called once per row by the generated `FromValue`, so by every query returning the entity.
+pub trait AfterLoad {
+    fn after_load(&mut self);
 }
 
 pub trait Table {
@@ -97,6 +113,8 @@ pub struct FieldName {
     pub select: bool,
     pub fill: Option<Fill>,
     pub field_type: FieldType,
+    /// the column type of the generated DDL, e.g. `VARCHAR(255)`
+    pub sql_type: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -132,6 +150,7 @@ impl FieldName {
                 select: true,
                 fill: None,
                 field_type: FieldType::TableField,
+                sql_type: None,
             }
         } else {
             FieldName {
@@ -142,10 +161,17 @@ impl FieldName {
                 select: true,
                 fill: None,
                 field_type: FieldType::TableField,
+                sql_type: None,
             }
         }
     }
 
+    /// whether the column is part of an insert clause, the `db_default` and `auto` filled columns
+    /// are left out so the database applies its default or generated value
+    pub fn insertable(&self) -> bool {
+        self.exist && !matches!(self.fill.as_ref().map(|fill| fill.mode.as_str()), Some("db_default") | Some("auto"))
+    }
+
     /// return the long name of the table using schema.table_name
     pub fn complete_name(&self) -> String {
         match self.table {
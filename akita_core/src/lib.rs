@@ -7,6 +7,8 @@ mod value;
 mod param;
 mod macros;
 mod types;
+#[cfg(feature = "akita-mysql")]
+mod mysql_value;
 pub mod comm;
 
 
@@ -0,0 +1,154 @@
+//!
+//! Conversions between akita `Value` and `mysql::Value`.
+//!
+use std::convert::TryFrom;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
+
+use crate::{Array, ConvertError, FromValue, Interval, Value};
+
+const MICROSECONDS_PER_DAY: i64 = 86_400_000_000;
+
+impl TryFrom<Value> for mysql::Value {
+    type Error = ConvertError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        let value = match v {
+            Value::Nil => mysql::Value::NULL,
+            Value::Bool(v) => mysql::Value::Int(v as i64),
+            Value::Tinyint(v) => mysql::Value::Int(v as i64),
+            Value::Smallint(v) => mysql::Value::Int(v as i64),
+            Value::Int(v) => mysql::Value::Int(v as i64),
+            Value::Bigint(v) => mysql::Value::Int(v),
+            Value::Float(v) => mysql::Value::Float(v),
+            Value::Double(v) => mysql::Value::Double(v),
+            Value::BigDecimal(v) => mysql::Value::Bytes(v.to_string().into_bytes()),
+            Value::Blob(v) => mysql::Value::Bytes(v),
+            Value::Char(v) => mysql::Value::Bytes(v.to_string().into_bytes()),
+            Value::Text(v) => mysql::Value::Bytes(v.into_bytes()),
+            Value::Json(v) => mysql::Value::Bytes(serde_json::to_string(&v).unwrap_or_default().into_bytes()),
+            Value::Uuid(v) => mysql::Value::Bytes(v.as_bytes().to_vec()),
+            Value::Date(v) => mysql::Value::Date(v.year() as u16, v.month() as u8, v.day() as u8, 0, 0, 0, 0),
+            Value::Time(v) => mysql::Value::Time(false, 0, v.hour() as u8, v.minute() as u8, v.second() as u8, v.nanosecond() / 1_000),
+            Value::DateTime(v) => mysql::Value::Date(v.year() as u16, v.month() as u8, v.day() as u8, v.hour() as u8, v.minute() as u8, v.second() as u8, v.nanosecond() / 1_000),
+            Value::Timestamp(v) => {
+                let v = v.naive_utc();
+                mysql::Value::Date(v.year() as u16, v.month() as u8, v.day() as u8, v.hour() as u8, v.minute() as u8, v.second() as u8, v.nanosecond() / 1_000)
+            }
+            Value::Interval(ref interval) if interval.months == 0 => {
+                let total = interval.days as i64 * MICROSECONDS_PER_DAY + interval.microseconds;
+                let neg = total < 0;
+                let total = total.abs();
+                let days = (total / MICROSECONDS_PER_DAY) as u32;
+                let rest = total % MICROSECONDS_PER_DAY;
+                let seconds = rest / 1_000_000;
+                mysql::Value::Time(neg, days, (seconds / 3600) as u8, (seconds % 3600 / 60) as u8, (seconds % 60) as u8, (rest % 1_000_000) as u32)
+            }
+            Value::Array(v) => {
+                let value = match v {
+                    Array::Int(vv) => serde_json::to_string(&vv),
+                    Array::Float(vv) => serde_json::to_string(&vv),
+                    Array::Text(vv) => serde_json::to_string(&vv),
+                };
+                mysql::Value::Bytes(value.unwrap_or_default().into_bytes())
+            }
+            Value::Object(_) => {
+                let value = serde_json::Value::from_value_opt(&v).map_err(|_| ConvertError::NotSupported(format!("{:?}", v), "mysql::Value".to_string()))?;
+                mysql::Value::Bytes(serde_json::to_string(&value).unwrap_or_default().into_bytes())
+            }
+            _ => return Err(ConvertError::NotSupported(format!("{:?}", v), "mysql::Value".to_string())),
+        };
+        Ok(value)
+    }
+}
+
+/// The conversion back is lossy, mysql values carry less type information than akita ones:
+/// the integers and `Bool` come back as `Bigint`, the text types, `BigDecimal` and `Json` as `Text`,
+/// `Uuid` as `Text` or `Blob` depending on whether its bytes are valid UTF-8, and a `DateTime` at midnight as `Date`.
+impl From<mysql::Value> for Value {
+    fn from(v: mysql::Value) -> Self {
+        match v {
+            mysql::Value::NULL => Value::Nil,
+            mysql::Value::Bytes(v) => match String::from_utf8(v) {
+                Ok(v) => Value::Text(v),
+                Err(e) => Value::Blob(e.into_bytes()),
+            },
+            mysql::Value::Int(v) => Value::Bigint(v),
+            mysql::Value::UInt(v) => match v.to_i64() {
+                Some(v) => Value::Bigint(v),
+                None => Value::BigDecimal(BigDecimal::from(v)),
+            },
+            mysql::Value::Float(v) => Value::Float(v),
+            mysql::Value::Double(v) => Value::Double(v),
+            mysql::Value::Date(year, month, day, hour, minute, second, micros) => {
+                let date = NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32);
+                match date {
+                    Some(date) if hour == 0 && minute == 0 && second == 0 && micros == 0 => Value::Date(date),
+                    Some(date) => date.and_hms_micro_opt(hour as u32, minute as u32, second as u32, micros).map(Value::DateTime).unwrap_or(Value::Nil),
+                    // zero dates such as `0000-00-00` have no chrono representation
+                    None => Value::Nil,
+                }
+            }
+            mysql::Value::Time(neg, days, hour, minute, second, micros) => {
+                if !neg && days == 0 {
+                    if let Some(time) = NaiveTime::from_hms_micro_opt(hour as u32, minute as u32, second as u32, micros) {
+                        return Value::Time(time)
+                    }
+                }
+                let microseconds = ((hour as i64 * 60 + minute as i64) * 60 + second as i64) * 1_000_000 + micros as i64;
+                let sign = if neg { -1 } else { 1 };
+                Value::Interval(Interval::new(sign * microseconds, sign as i32 * days as i32, 0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{Interval, Value};
+
+    #[test]
+    fn mysql_value_round_trip() {
+        let values = vec![
+            Value::Nil,
+            Value::Bigint(-42),
+            Value::Float(1.5),
+            Value::Double(3.25),
+            Value::Text("akita".to_string()),
+            Value::Blob(vec![0, 159, 146, 150]),
+            Value::Date(NaiveDate::from_ymd_opt(2021, 12, 8).unwrap()),
+            Value::DateTime(NaiveDate::from_ymd_opt(2021, 12, 8).unwrap().and_hms_micro_opt(10, 21, 3, 500).unwrap()),
+            Value::Time(NaiveTime::from_hms_micro_opt(10, 21, 3, 500).unwrap()),
+            Value::Interval(Interval::new(-3_600_000_000, -2, 0)),
+        ];
+        for value in values {
+            let mysql_value = mysql::Value::try_from(value.clone()).unwrap();
+            assert_eq!(Value::from(mysql_value), value);
+        }
+        assert_eq!(mysql::Value::try_from(Value::Int(7)).unwrap(), mysql::Value::Int(7));
+        assert!(mysql::Value::try_from(Value::Interval(Interval::new(0, 0, 1))).is_err());
+    }
+
+    #[test]
+    fn mysql_value_lossy_round_trip() {
+        let midnight = NaiveDate::from_ymd_opt(2021, 12, 8).unwrap();
+        let decimal = bigdecimal::BigDecimal::parse_bytes(b"19.90", 10).unwrap();
+        let uuid = uuid::Uuid::from_bytes([0xff; 16]);
+        let values = vec![
+            (Value::Bool(true), Value::Bigint(1)),
+            (Value::Int(7), Value::Bigint(7)),
+            (Value::Char('a'), Value::Text("a".to_string())),
+            (Value::BigDecimal(decimal), Value::Text("19.90".to_string())),
+            (Value::Json(serde_json::json!({"a": 1})), Value::Text("{\"a\":1}".to_string())),
+            (Value::Uuid(uuid), Value::Blob(vec![0xff; 16])),
+            (Value::DateTime(midnight.and_hms_opt(0, 0, 0).unwrap()), Value::Date(midnight)),
+        ];
+        for (value, expected) in values {
+            let mysql_value = mysql::Value::try_from(value).unwrap();
+            assert_eq!(Value::from(mysql_value), expected);
+        }
+    }
+}
@@ -12,6 +12,18 @@ pub enum Params {
 //     fn to_param(&self) -> Params;
 // }
 
+#[cfg(feature = "akita-logging")]
+impl Params {
+    /// render the bound parameters as json for structured logs
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Params::Nil => serde_json::Value::Array(Vec::new()),
+            Params::Vector(values) => serde_json::Value::Array(values.iter().map(|v| v.to_json()).collect()),
+            Params::Custom(values) => serde_json::Value::Object(values.iter().map(|(k, v)| (k.to_owned(), v.to_json())).collect()),
+        }
+    }
+}
+
 impl From<Vec<Value>> for Params {
     fn from(x: Vec<Value>) -> Params {
         Params::Vector(x)
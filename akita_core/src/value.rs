@@ -1,4 +1,4 @@
-use std::{any::type_name, fmt, mem};
+use std::{any::type_name, fmt, mem, net::{IpAddr, Ipv4Addr, Ipv6Addr}};
 use bigdecimal::{BigDecimal, ToPrimitive};
 use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Serialize, Deserialize};
@@ -289,6 +289,55 @@ impl fmt::Display for Value {
 }
 
 
+/// blobs longer than this are cut short when rendered as json
+#[cfg(feature = "akita-logging")]
+const JSON_BLOB_LIMIT: usize = 64;
+
+#[cfg(feature = "akita-logging")]
+impl Value {
+    /// a json representation for structured logs, bytes as base64 (truncated) and dates as iso strings
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Nil => serde_json::Value::Null,
+            Value::Bool(v) => serde_json::Value::Bool(*v),
+            Value::Tinyint(v) => serde_json::Value::from(*v),
+            Value::Smallint(v) => serde_json::Value::from(*v),
+            Value::Int(v) => serde_json::Value::from(*v),
+            Value::Bigint(v) => serde_json::Value::from(*v),
+            Value::Float(v) => serde_json::Value::from(*v),
+            Value::Double(v) => serde_json::Value::from(*v),
+            Value::BigDecimal(v) => serde_json::Value::String(v.to_string()),
+            Value::Blob(v) => {
+                if v.len() > JSON_BLOB_LIMIT {
+                    serde_json::Value::String(format!("{}...({} bytes)", base64::encode(&v[..JSON_BLOB_LIMIT]), v.len()))
+                } else {
+                    serde_json::Value::String(base64::encode(v))
+                }
+            }
+            Value::Char(v) => serde_json::Value::String(v.to_string()),
+            Value::Text(v) => serde_json::Value::String(v.to_owned()),
+            Value::Json(v) => v.to_owned(),
+            Value::Uuid(v) => serde_json::Value::String(v.to_string()),
+            Value::Date(v) => serde_json::Value::String(v.format("%Y-%m-%d").to_string()),
+            Value::Time(v) => serde_json::Value::String(v.format("%H:%M:%S%.f").to_string()),
+            Value::DateTime(v) => serde_json::Value::String(v.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+            Value::Timestamp(v) => serde_json::Value::String(v.to_rfc3339()),
+            Value::Interval(v) => serde_json::json!({
+                "months": v.months,
+                "days": v.days,
+                "microseconds": v.microseconds,
+            }),
+            Value::Object(data) => serde_json::Value::Object(data.iter().map(|(k, v)| (k.to_owned(), v.to_json())).collect()),
+            Value::Array(array) => match array {
+                Array::Int(v) => serde_json::Value::from(v.to_owned()),
+                Array::Float(v) => serde_json::Value::from(v.to_owned()),
+                Array::Text(v) => serde_json::Value::from(v.to_owned()),
+            },
+        }
+    }
+}
+
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Array {
     /*
@@ -380,6 +429,63 @@ impl_to_value!(NaiveTime, Time);
 impl_to_value!(DateTime<Utc>, Timestamp);
 impl_to_value!(NaiveDateTime, DateTime);
 
+impl ToValue for IpAddr {
+    fn to_value(&self) -> Value {
+        Value::Text(self.to_string())
+    }
+}
+
+impl ToValue for Ipv4Addr {
+    fn to_value(&self) -> Value {
+        Value::Text(self.to_string())
+    }
+}
+
+impl ToValue for Ipv6Addr {
+    fn to_value(&self) -> Value {
+        Value::Text(self.to_string())
+    }
+}
+
+/// The packed binary form of an ip address, as `INET6_ATON` stores it in a `VARBINARY(16)` column:
+/// 4 bytes for v4 and 16 bytes for v6. Used for the fields marked with `#[field(inet)]`.
+pub trait ToInet {
+    fn to_inet(&self) -> Value;
+}
+
+impl ToInet for IpAddr {
+    fn to_inet(&self) -> Value {
+        match self {
+            IpAddr::V4(ip) => ip.to_inet(),
+            IpAddr::V6(ip) => ip.to_inet(),
+        }
+    }
+}
+
+impl ToInet for Ipv4Addr {
+    fn to_inet(&self) -> Value {
+        Value::Blob(self.octets().to_vec())
+    }
+}
+
+impl ToInet for Ipv6Addr {
+    fn to_inet(&self) -> Value {
+        Value::Blob(self.octets().to_vec())
+    }
+}
+
+impl<T> ToInet for Option<T>
+where
+    T: ToInet,
+{
+    fn to_inet(&self) -> Value {
+        match self {
+            Some(v) => v.to_inet(),
+            None => Value::Nil,
+        }
+    }
+}
+
 impl ToValue for &str {
     fn to_value(&self) -> Value {
         Value::Text(self.to_string())
@@ -540,9 +646,12 @@ macro_rules! impl_from_value_numeric {
                     $(Value::$variant(ref v) => Ok(v.to_owned() as $ty),
                     )*
                     Value::BigDecimal(ref v) => Ok(v.$method().unwrap_or_default()),
-                    Value::Object(ref v) => {
-                        let (_, v) = v.first().unwrap_or((&String::default(), &Value::Nil));
-                        Ok(<$ty>::from_value(v))
+                    Value::Text(ref v) => v.trim().parse::<$ty>().map_err(|err| ConvertError::Parse(v.to_owned(), err.to_string()).into()),
+                    Value::Object(ref v) => match v.first() {
+                        // a NULL aggregate (`MAX`/`SUM` over no rows) can't be a number, it reads as `Option<$ty>` instead
+                        Some((_, Value::Nil)) | None => Err(AkitaDataError::ConvertError(ConvertError::NotSupported(
+                            format!("NULL, use Option<{}> for a nullable value", $ty_name), $ty_name.into()))),
+                        Some((_, v)) => <$ty>::from_value_opt(v),
                     },
                     _ => Err(AkitaDataError::ConvertError(ConvertError::NotSupported(format!("{:?}", v), $ty_name.into()))),
                 }
@@ -608,7 +717,11 @@ impl FromValue for String {
                 let data: IndexMap<String, Value> = obj.to_owned();
                 if data.len() > 0 {
                     let (_k, v) = data.get_index(0).unwrap();
-                    Ok(v.as_str().to_owned().unwrap_or_default().to_string())
+                    match v {
+                        Value::Nil => Ok(String::default()),
+                        // e.g. a DECIMAL column, keep its exact text instead of dropping it
+                        _ => String::from_value_opt(v),
+                    }
                 } else {
                     Ok(String::default())
                 }
@@ -702,7 +815,7 @@ impl FromValue for serde_json::Value {
 impl FromValue for DateTime<Utc> {
     fn from_value_opt(v: &Value) -> Result<Self, AkitaDataError> {
         match *v {
-            Value::Text(ref v) => Ok(DateTime::<Utc>::from_utc(parse_naive_date_time(v), Utc)),
+            Value::Text(ref v) => Ok(DateTime::<Utc>::from_utc(parse_naive_date_time(v)?, Utc)),
             Value::DateTime(v) => Ok(DateTime::<Utc>::from_utc(v, Utc)),
             Value::Timestamp(v) => Ok(v),
             _ => Err(AkitaDataError::ConvertError(ConvertError::NotSupported(
@@ -716,7 +829,7 @@ impl FromValue for DateTime<Utc> {
 impl FromValue for NaiveDateTime {
     fn from_value_opt(v: &Value) -> Result<Self, AkitaDataError> {
         match *v {
-            Value::Text(ref v) => Ok(parse_naive_date_time(v)),
+            Value::Text(ref v) => Ok(parse_naive_date_time(v)?),
             Value::DateTime(v) => Ok(v),
             _ => Err(AkitaDataError::ConvertError(ConvertError::NotSupported(
                 format!("{:?}", v),
@@ -733,11 +846,59 @@ where
     fn from_value_opt(v: &Value) -> Result<Self, AkitaDataError> {
         match *v {
             Value::Nil => Ok(None),
+            // a single column row holding NULL, e.g. `SELECT MAX(id) FROM t_user` on an empty table
+            Value::Object(ref map) if map.len() == 1 && map.values().all(Value::is_nil) => Ok(None),
             _ => FromValue::from_value_opt(v).map(Some),
         }
     }
 }
 
+/// an ip address from its text or from the packed binary form of `INET6_ATON`
+fn ip_from_value(v: &Value, ty: &str) -> Result<IpAddr, AkitaDataError> {
+    let not_supported = || AkitaDataError::ConvertError(ConvertError::NotSupported(format!("{:?}", v), ty.to_string()));
+    match *v {
+        Value::Text(ref v) => v.trim().parse::<IpAddr>().map_err(|_| not_supported()),
+        Value::Blob(ref bytes) => match bytes.len() {
+            4 => Ok(IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]))),
+            16 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(bytes);
+                Ok(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            _ => Err(not_supported()),
+        },
+        Value::Object(ref v) => {
+            let (_, v) = v.first().unwrap_or((&String::default(), &Value::Nil));
+            ip_from_value(v, ty)
+        }
+        _ => Err(not_supported()),
+    }
+}
+
+impl FromValue for IpAddr {
+    fn from_value_opt(v: &Value) -> Result<Self, AkitaDataError> {
+        ip_from_value(v, "IpAddr")
+    }
+}
+
+impl FromValue for Ipv4Addr {
+    fn from_value_opt(v: &Value) -> Result<Self, AkitaDataError> {
+        match ip_from_value(v, "Ipv4Addr")? {
+            IpAddr::V4(ip) => Ok(ip),
+            IpAddr::V6(ip) => ip.to_ipv4().ok_or_else(|| AkitaDataError::ConvertError(ConvertError::NotSupported(ip.to_string(), "Ipv4Addr".to_string()))),
+        }
+    }
+}
+
+impl FromValue for Ipv6Addr {
+    fn from_value_opt(v: &Value) -> Result<Self, AkitaDataError> {
+        match ip_from_value(v, "Ipv6Addr")? {
+            IpAddr::V4(ip) => Ok(ip.to_ipv6_mapped()),
+            IpAddr::V6(ip) => Ok(ip),
+        }
+    }
+}
+
 impl<T> FromValue for &T
 where
     T: FromValue,
@@ -758,22 +919,28 @@ impl FromValue for Value
     }
 }
 
-
-fn parse_naive_date_time(v: &str) -> NaiveDateTime {
-    let ts = NaiveDateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S");
-    if let Ok(ts) = ts {
-        ts
-    } else {
-        let ts = NaiveDateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S%.3f");
-        if let Ok(ts) = ts {
-            ts
-        } else {
-            panic!("unable to parse timestamp: {}", v);
+/// A whole row as its values, positionally in the column order of the SELECT,
+/// the column names are in `Rows::columns`.
+impl FromValue for Vec<Value> {
+    fn from_value_opt(v: &Value) -> Result<Self, AkitaDataError> {
+        match *v {
+            Value::Object(ref data) => Ok(data.values().cloned().collect()),
+            Value::Nil => Ok(Vec::new()),
+            _ => Err(AkitaDataError::ConvertError(ConvertError::NotSupported(
+                format!("{:?}", v),
+                "Vec<Value>".to_string(),
+            ))),
         }
     }
 }
 
 
+fn parse_naive_date_time(v: &str) -> Result<NaiveDateTime, ConvertError> {
+    NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S").or_else(|_| NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.3f"))
+        .map_err(|err| ConvertError::Parse(v.to_string(), err.to_string()))
+}
+
+
 
 macro_rules! take_or_place {
     ($row:expr, $index:expr, $t:ident) => (
@@ -1096,4 +1263,94 @@ impl <V> ToValue for IndexMap<String, V> where V: ToValue {
         Value::Object(map)
 
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::{FromValue, Rows, Value};
+
+    #[test]
+    fn row_into_values() {
+        let mut rows = Rows::new(vec!["b".to_string(), "a".to_string()]);
+        rows.push(vec![Value::Int(2), Value::Text("akita".to_string())]);
+        let row = rows.iter().next().map(|data| Vec::<Value>::from_value(&data)).unwrap_or_default();
+        assert_eq!(row, vec![Value::Int(2), Value::Text("akita".to_string())]);
+        assert_eq!(rows.column_info().iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn rows_skip_empty() {
+        let mut rows = Rows::new(vec!["id".to_string()]);
+        rows.push(vec![Value::Int(1)]);
+        rows.push(vec![]);
+        rows.push(vec![Value::Int(2)]);
+        assert_eq!((rows.iter().len(), rows.iter().count()), (2, 2));
+        assert_eq!(rows.clone().into_iter().len(), 2);
+        assert_eq!(rows.iter().count(), rows.into_iter().count());
+    }
+
+    #[test]
+    fn decimal_into_string() {
+        // DECIMAL(10,2) arrives as its text bytes and keeps the scale
+        let decimal = bigdecimal::BigDecimal::parse_bytes(b"19.90", 10).map(Value::BigDecimal).unwrap_or(Value::Nil);
+        assert_eq!(String::from_value(&decimal), "19.90");
+        let mut row = Value::new_object();
+        row.insert_obj_value("price", &decimal);
+        assert_eq!(String::from_value(&row), "19.90");
+    }
+
+    #[test]
+    fn null_aggregate() {
+        let mut max = Value::new_object();
+        max.insert_obj_value("max(id)", &Value::Nil);
+        assert!(i64::from_value_opt(&max).is_err());
+        assert_eq!(Option::<i64>::from_value_opt(&max).ok(), Some(None));
+        let mut count = Value::new_object();
+        count.insert_obj_value("count(id)", &Value::Bigint(0));
+        assert_eq!(Option::<i64>::from_value_opt(&count).ok(), Some(Some(0)));
+    }
+
+    #[test]
+    fn parse_text() {
+        use chrono::NaiveDateTime;
+        assert_eq!(i64::from_value_opt(&Value::Text(" 42 ".to_string())).ok(), Some(42));
+        assert_eq!(f64::from_value_opt(&Value::Text("1.5".to_string())).ok(), Some(1.5));
+        assert!(i32::from_value_opt(&Value::Text("4x2".to_string())).is_err());
+        assert!(u8::from_value_opt(&Value::Text("300".to_string())).is_err());
+        assert!(f32::from_value_opt(&Value::Text("one".to_string())).is_err());
+        assert!(NaiveDateTime::from_value_opt(&Value::Text("2021-08-01 12:30:00.250".to_string())).is_ok());
+        assert!(NaiveDateTime::from_value_opt(&Value::Text("2021-13-01".to_string())).is_err());
+        match i32::from_value_opt(&Value::Text("4x2".to_string())) {
+            Err(crate::AkitaDataError::ConvertError(crate::ConvertError::Parse(text, _))) => assert_eq!(text, "4x2"),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "akita-logging")]
+    fn to_json() {
+        use chrono::NaiveDate;
+        assert_eq!(Value::Int(1).to_json(), serde_json::json!(1));
+        assert_eq!(Value::Nil.to_json(), serde_json::Value::Null);
+        assert_eq!(Value::Date(NaiveDate::from_ymd_opt(2021, 8, 1).unwrap()).to_json(), serde_json::json!("2021-08-01"));
+        assert_eq!(Value::DateTime(NaiveDate::from_ymd_opt(2021, 8, 1).and_then(|d| d.and_hms_opt(12, 30, 0)).unwrap()).to_json(), serde_json::json!("2021-08-01T12:30:00"));
+        assert_eq!(Value::Blob(vec![1, 2, 3]).to_json(), serde_json::json!("AQID"));
+        let blob = Value::Blob(vec![0; 1024]).to_json();
+        assert!(blob.as_str().unwrap_or_default().ends_with("...(1024 bytes)"));
+    }
+
+    #[test]
+    fn ip_round_trip() {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+        use crate::{ToInet, ToValue};
+        let v4 = Ipv4Addr::new(192, 168, 1, 10);
+        let v6 = "2001:db8::ff00:42:8329".parse::<Ipv6Addr>().unwrap();
+        assert_eq!(v4.to_inet(), Value::Blob(vec![192, 168, 1, 10]));
+        assert_eq!(Ipv4Addr::from_value(&v4.to_inet()), v4);
+        assert_eq!(Ipv4Addr::from_value(&v4.to_value()), v4);
+        assert_eq!(Ipv6Addr::from_value(&v6.to_inet()), v6);
+        assert_eq!(Ipv6Addr::from_value(&v6.to_value()), v6);
+        assert_eq!(IpAddr::from_value(&IpAddr::V6(v6).to_inet()), IpAddr::V6(v6));
+        assert_eq!(IpAddr::from_value(&IpAddr::V4(v4).to_value()), IpAddr::V4(v4));
+        assert!(IpAddr::from_value_opt(&Value::Blob(vec![1, 2, 3])).is_err());
+    }
+}
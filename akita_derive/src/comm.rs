@@ -106,6 +106,9 @@ pub enum FieldExtra {
         argument: Option<CustomArgument>,
     },
     NumericScale(ValueOrPath<u64>),
+    Inet,
+    SqlType(String),
+    AfterLoad,
 }
 
 /// This struct stores information about defined custom arguments that will be passed in
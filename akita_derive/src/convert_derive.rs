@@ -2,35 +2,56 @@ use proc_macro::{TokenStream};
 use quote::quote;
 use syn::{self, DeriveInput};
 
-use crate::{util::{get_field_default_value, collect_field_info}, comm::FieldInformation};
+use crate::{util::{get_field_default_value, collect_field_info}, comm::{FieldExtra, FieldInformation}};
 
 pub fn impl_from_akita(input: TokenStream) -> TokenStream {
     let ast = syn::parse::<DeriveInput>(input).unwrap();
+    if is_newtype(&ast) {
+        let name = &ast.ident;
+        // `struct UserId(i64)` converts like its inner value
+        return quote!(
+            impl akita::core::FromValue for #name {
+
+                fn from_value_opt(data: &akita::core::Value) -> Result<Self, akita::core::AkitaDataError> {
+                    akita::core::FromValue::from_value_opt(data).map(#name)
+                }
+            }
+        ).into();
+    }
     let generics = &ast.generics;
     let fields = collect_field_info(&ast);
     let struct_info = &ast.ident;
-    let res = build_from_akita(struct_info, generics, &fields);
+    let res = build_from_akita(struct_info, generics, &fields, false);
     res.into()
 }
 
-pub fn build_from_akita(name: &syn::Ident, _generics: &syn::Generics, fields: &Vec<FieldInformation>) -> proc_macro2::TokenStream {
+/// whether the input is a single field tuple struct such as `struct UserId(i64)`
+fn is_newtype(ast: &DeriveInput) -> bool {
+    matches!(ast.data, syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Unnamed(ref fields), .. }) if fields.unnamed.len() == 1)
+}
+
+pub fn build_from_akita(name: &syn::Ident, _generics: &syn::Generics, fields: &Vec<FieldInformation>, after_load: bool) -> proc_macro2::TokenStream {
     let from_fields: Vec<proc_macro2::TokenStream> = fields
         .iter()
         .map(|field| {
             let field_name = &field.name;
             let field_info = field.field.ident.as_ref().unwrap();
             let default_value = get_field_default_value(&field.field.ty, field.field.ident.as_ref().unwrap());
-            quote!( #field_info: match data.get_obj(#field_name) { Ok(v) => v, Err(_) => { #default_value } },)
+            quote!( #field_info: match data.get_obj(#field_name) { Ok(v) => v, Err(_err) => { #default_value } },)
         })
         .collect();
 
+    // the hook needs a mutable entity, skip the `mut` otherwise so the generated code stays warning free
+    let (binding, after_load) = if after_load { (quote!(mut entity), quote!( akita::core::AfterLoad::after_load(&mut entity); )) } else { (quote!(entity), quote!()) };
     quote!(
         impl akita::core::FromValue for #name {
 
             fn from_value_opt(data: &akita::core::Value) -> Result<Self, akita::core::AkitaDataError> {
-                Ok(#name {
+                let #binding = #name {
                     #(#from_fields)*
-                })
+                };
+                #after_load
+                Ok(entity)
             }
         }
     )
@@ -38,6 +59,17 @@ pub fn build_from_akita(name: &syn::Ident, _generics: &syn::Generics, fields: &V
 
 pub fn impl_to_akita(input: TokenStream) -> TokenStream {
     let ast = syn::parse::<DeriveInput>(input).unwrap();
+    if is_newtype(&ast) {
+        let name = &ast.ident;
+        return quote!(
+            impl akita::core::ToValue for #name {
+
+                fn to_value(&self) -> akita::core::Value {
+                    akita::core::ToValue::to_value(&self.0)
+                }
+            }
+        ).into();
+    }
     let generics = &ast.generics;
     let fields = collect_field_info(&ast);
     let struct_info = &ast.ident;
@@ -51,7 +83,12 @@ pub fn build_to_akita(name: &syn::Ident, generics: &syn::Generics, fields: &Vec<
         .map(|field| {
             let field_name = &field.name;
             let field_info = field.field.ident.as_ref().unwrap();
-            quote!( data.insert_obj(#field_name, &self.#field_info );)
+            if field.extra.iter().any(|extra| matches!(extra, FieldExtra::Inet)) {
+                // packed binary form of the ip address
+                quote!( data.insert_obj_value(#field_name, &akita::core::ToInet::to_inet(&self.#field_info));)
+            } else {
+                quote!( data.insert_obj(#field_name, &self.#field_info );)
+            }
         })
         .collect();
     let res = quote!(
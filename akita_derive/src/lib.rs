@@ -16,6 +16,9 @@ mod comm;
 
 
 /// Generate table info data
+///
+/// A single field tuple struct such as `struct UserId(i64)` converts like its inner value,
+/// so the newtype can be used as a `#[table_id]` or a field directly.
 #[proc_macro_derive(FromValue)]
 pub fn from_akita(input: TokenStream) -> TokenStream {
     convert_derive::impl_from_akita(input)
@@ -41,6 +44,9 @@ pub fn to_akita(input: TokenStream) -> TokenStream {
 ///     age: i32,
 /// }
 /// ```
+///
+/// An `IpAddr`/`Ipv4Addr`/`Ipv6Addr` field is stored as its text, mark it with `#[field(inet)]`
+/// to store the packed binary form of `INET6_ATON` in a `VARBINARY(16)` column instead.
 /// 
 #[proc_macro_derive(AkitaTable, attributes(field, table, table_id, fill))]
 #[proc_macro_error]
@@ -117,6 +117,8 @@ fn filter_args_context_id(
         if do_continue {
             continue;
         }
+        // bind by the plain name, a `mut` pattern is not an expression
+        let item = Ident::new(&item_ident_name, Span::call_site());
         sql_args_gen = quote! {
              #sql_args_gen
              akita_args.push(#item.to_value());
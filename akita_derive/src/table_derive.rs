@@ -1,7 +1,7 @@
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{DeriveInput};
-use crate::{convert_derive::{build_to_akita, build_from_akita}, comm::{ FieldExtra},util::{ find_struct_annotions, collect_field_info, to_snake_name}};
+use crate::{convert_derive::{build_to_akita, build_from_akita}, comm::{ FieldExtra},util::{ find_struct_annotions, collect_field_info, get_field_sql_type, to_snake_name}};
 
 pub fn impl_get_table(input: TokenStream) -> TokenStream {
     let derive_input = syn::parse::<DeriveInput>(input).unwrap();
@@ -22,6 +22,7 @@ fn parse_table(ast: &syn::DeriveInput) -> TokenStream {
    if table_name.is_empty() {
        table_name = to_snake_name(struct_name);
    }
+    let mut select_columns = Vec::new();
     let from_fields: Vec<proc_macro2::TokenStream> = fields
         .iter()
         .map(|field| {
@@ -31,6 +32,7 @@ fn parse_table(ast: &syn::DeriveInput) -> TokenStream {
             let mut identify = false;
             let mut fill_function = String::default();
             let mut fill_mode = None;
+            let mut sql_type = get_field_sql_type(&field.field.ty).map(String::from);
 
             for extra in field.extra.iter() {
                 match extra {
@@ -51,16 +53,26 @@ fn parse_table(ast: &syn::DeriveInput) -> TokenStream {
                     FieldExtra::TableId(_) => {
                         identify = true;
                     }
+                    FieldExtra::SqlType(v) => {
+                        sql_type = Some(v.clone());
+                    }
                     _ => { }
                 }
             }
 
+            if exist && select {
+                select_columns.push(format!("`{}`", name));
+            }
             let field_type = if identify { quote!(akita::FieldType::TableId("none".to_string())) } else { quote!(akita::FieldType::TableField) };
             let fill_mode = fill_mode.unwrap_or(String::from("default")).to_lowercase();
-            let fill = if fill_function.is_empty() { quote! (None) } else { let fn_ident: syn::Path = syn::parse_str(&fill_function).unwrap(); quote! (akita::core::Fill {
+            let fill = if fill_function.is_empty() && (fill_mode.eq("db_default") || fill_mode.eq("auto")) { quote! (akita::core::Fill {
+                        value: None,
+                        mode: #fill_mode.to_string()
+                    }.into()) } else if fill_function.is_empty() { quote! (None) } else { let fn_ident: syn::Path = syn::parse_str(&fill_function).unwrap(); quote! (akita::core::Fill {
                         value: Some(#fn_ident().to_value()),
                         mode: #fill_mode.to_string()
                     }.into()) };
+            let sql_type = match sql_type { Some(sql_type) => quote!(Some(#sql_type.to_string())), None => quote!(None) };
 
             quote!(
                 akita::core::FieldName {
@@ -71,12 +83,15 @@ fn parse_table(ast: &syn::DeriveInput) -> TokenStream {
                     fill: #fill,
                     select: #select,
                     exist: #exist,
+                    sql_type: #sql_type,
                 },
             )
         }).collect();
+    let select_columns = select_columns.join(", ");
     let impl_mapper = impl_table_mapper(struct_info);
     let impl_to_akita = build_to_akita(struct_info, generics, &fields);
-    let impl_from_akita = build_from_akita(struct_info, generics, &fields);
+    let after_load = structs.iter().any(|st| matches!(st, FieldExtra::AfterLoad));
+    let impl_from_akita = build_from_akita(struct_info, generics, &fields, after_load);
 
     quote!(
         #impl_mapper
@@ -101,6 +116,10 @@ fn parse_table(ast: &syn::DeriveInput) -> TokenStream {
                     #(#from_fields)*
                 ]
             }
+
+            fn select_columns() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Borrowed(#select_columns)
+            }
         }
     ).into()
 }
@@ -115,7 +134,7 @@ fn impl_table_mapper(name: &syn::Ident) -> proc_macro2::TokenStream {
                 entity_manager.save(self)
             }
 
-            fn insert_batch<M: akita::AkitaMapper>(datas: &[&Self::Item], entity_manager: &M) -> Result<(), akita::AkitaError> where Self::Item : akita::core::GetTableName + akita::core::GetFields {
+            fn insert_batch<M: akita::AkitaMapper>(datas: &[&Self::Item], entity_manager: &M) -> Result<u64, akita::AkitaError> where Self::Item : akita::core::GetTableName + akita::core::GetFields {
                 entity_manager.save_batch::<Self::Item>(datas)
             }
 
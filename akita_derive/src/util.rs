@@ -69,11 +69,39 @@ pub fn get_field_default_value(ty: &Type, ident: &Ident) -> proc_macro2::TokenSt
             "NaiveDateTime" => quote!(Local::now().naive_local()),
             "Vec" => quote!(Vec::new()),
             "Value" => quote!(serde_json::Value::default()),
-            _ => quote!(None)
+            "IpAddr" => quote!(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED)),
+            "Ipv4Addr" => quote!(std::net::Ipv4Addr::UNSPECIFIED),
+            "Ipv6Addr" => quote!(std::net::Ipv6Addr::UNSPECIFIED),
+            // no default for other types such as a newtype id, a missing value is an error
+            _ => quote!(return Err(_err))
         }
     }
 }
 
+/// The column type of the generated DDL for a rust type, `None` for the types without a natural mapping.
+pub fn get_field_sql_type(ty: &Type) -> Option<&'static str> {
+    match get_field_type(ty).unwrap_or_default().as_str() {
+        "bool" => Some("BOOLEAN"),
+        "i8" | "u8" => Some("TINYINT"),
+        "i16" | "u16" => Some("SMALLINT"),
+        "i32" | "u32" => Some("INT"),
+        "i64" | "u64" | "isize" | "usize" => Some("BIGINT"),
+        "f32" => Some("FLOAT"),
+        "f64" => Some("DOUBLE"),
+        "BigDecimal" => Some("DECIMAL(38, 10)"),
+        "String" | "str" | "char" => Some("VARCHAR(255)"),
+        "NaiveDate" => Some("DATE"),
+        "NaiveTime" => Some("TIME"),
+        "NaiveDateTime" => Some("DATETIME"),
+        "DateTime" => Some("TIMESTAMP"),
+        "Uuid" => Some("CHAR(36)"),
+        "IpAddr" | "Ipv4Addr" | "Ipv6Addr" => Some("VARCHAR(45)"),
+        "Vec" => Some("BLOB"),
+        "Value" => Some("JSON"),
+        _ => None,
+    }
+}
+
 /// Finds all struct schema annotion
 pub fn find_struct_annotions(struct_attrs: &[syn::Attribute]) -> Vec<FieldExtra> {
     struct_attrs
@@ -103,7 +131,8 @@ pub fn find_struct_annotion(attr: &syn::Attribute) -> Vec<FieldExtra> {
                     syn::NestedMeta::Meta(ref item) => match *item {
                         // name
                         syn::Meta::Path(ref name) => {
-                            match name.get_ident().unwrap().to_string() {
+                            match name.get_ident().unwrap().to_string().as_ref() {
+                                "after_load" => extras.push(FieldExtra::AfterLoad),
                                 _ => {
                                     let mut ident = proc_macro2::TokenStream::new();
                                     name.to_tokens(&mut ident);
@@ -267,10 +296,11 @@ pub fn find_extra_for_field(
                         syn::NestedMeta::Meta(ref item) => match *item {
                             // name, exist, fill, select
                             syn::Meta::Path(ref name) => {
-                                match name.get_ident().unwrap().to_string() {
+                                match name.get_ident().unwrap().to_string().as_ref() {
                                     // "fill" => {
                                     //     extras.push(FieldExtra::Name());
                                     // }
+                                    "inet" => extras.push(FieldExtra::Inet),
                                     _ => {
                                         let mut ident = proc_macro2::TokenStream::new();
                                         name.to_tokens(&mut ident);
@@ -300,6 +330,12 @@ pub fn find_extra_for_field(
                                             None => error(lit.span(), "invalid argument for `name` annotion: only strings are allowed"),
                                         };
                                     }
+                                    "sql_type" => {
+                                        match lit_to_string(lit) {
+                                            Some(s) if !s.trim().is_empty() => extras.push(FieldExtra::SqlType(s)),
+                                            _ => error(lit.span(), "invalid argument for `sql_type` annotion: only non-empty strings are allowed"),
+                                        };
+                                    }
                                     "id_type" => {
                                         match lit_to_string(lit) {
                                             Some(s) => match s.to_lowercase().as_ref() {
@@ -552,11 +588,11 @@ pub fn extract_fill_custom(
                         "mode" => {
                             mode = match lit_to_string(lit) {
                                 Some(s) => match s.as_ref() {
-                                    "default" | "insert" | "update" => {
+                                    "default" | "insert" | "update" | "db_default" | "auto" => {
                                         Some(s)
                                     }
                                     _ => {
-                                        error(lit.span(), "invalid argument type for `mode` of `fill` annotion: expected `default`,`insert`,`update` ")
+                                        error(lit.span(), "invalid argument type for `mode` of `fill` annotion: expected `default`,`insert`,`update`,`db_default`,`auto` ")
                                     }
                                 },
                                 None => error(lit.span(), "invalid argument type for `mode` of `fill` annotion: expected a string")
@@ -597,10 +633,12 @@ pub fn extract_fill_custom(
         }
     }
 
-    if function.is_none() {
+    // the database fills the `db_default` and `auto` columns by itself
+    let omitted = matches!(mode.as_deref(), Some("db_default") | Some("auto"));
+    if function.is_none() && !omitted {
         error(attr.span(), "The annotion `custom` requires the `function` parameter.");
     }
-    let extra = FieldExtra::Fill { function: function.unwrap(), argument, mode };
+    let extra = FieldExtra::Fill { function: function.unwrap_or_default(), argument, mode };
     extra
 }
 
@@ -2,15 +2,17 @@
 //! Akita
 //!
 
-use akita_core::{FieldType, GetTableName};
+use std::{collections::{HashMap, HashSet}, hash::Hash, io::Write, ops::{Deref, DerefMut}, path::Path, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread::{self, ThreadId}, time::Duration};
+use akita_core::{FieldType, GetTableName, TableName, comm::split_sql_statements};
 use once_cell::sync::OnceCell;
 
 use crate::segment::ISegment;
 use crate::{AkitaError, AkitaMapper, IPage, Pool, Wrapper, database::DatabasePlatform, AkitaConfig};
 use crate::{cfg_if, Params, Rows, FromValue, Value, ToValue, GetFields};
 use crate::database::Platform;
-use crate::manager::{AkitaTransaction, build_insert_clause, build_update_clause};
+use crate::manager::{AkitaTransaction, quote_table_str, remove_by_ids_on, page_query, page_records_on, list_query, list_arc_on, count_group_by_on, build_bulk_insert_clause, infile_row, build_insert_clause, build_insert_values, build_update_clause, build_replace_clause, save_batch_on_conflict, warn_id_overflow};
 use crate::pool::{PlatformPool, PooledConnection};
+use crate::metrics;
 
 cfg_if! {if #[cfg(feature = "akita-mysql")]{
     use crate::platform::{mysql::{self, MysqlDatabase}};
@@ -20,11 +22,119 @@ cfg_if! {if #[cfg(feature = "akita-sqlite")]{
     use crate::platform::sqlite::{self, SqliteDatabase};
 }}
 
+cfg_if! {if #[cfg(feature = "akita-ddl")]{
+    use crate::manager::{build_create_table_clause, build_add_column_clause, quote_table_name};
+}}
+
+/// rows per `LOAD DATA` of `bulk_load`, bounding the in-memory file
+const BULK_INFILE_ROWS: usize = 100_000;
+
 #[allow(unused)]
 pub struct Akita{
     /// the connection pool
     pool: OnceCell<PlatformPool>,
     cfg: AkitaConfig,
+    /// the threads with a transaction of `start_transaction` open on this handle
+    pub(crate) transactions: OpenTransactions,
+    /// the single connection of a `with_schema` handle, lent out by `acquire`
+    pinned: Option<Mutex<Option<DatabasePlatform>>>,
+}
+
+/// A connection checked out by `acquire`, used as the `DatabasePlatform` it derefs to.
+/// Dropping it returns the connection to the pool, or to the `with_schema` handle it is pinned to.
+pub struct AkitaConnection<'a> {
+    conn: Option<DatabasePlatform>,
+    pinned: Option<&'a Mutex<Option<DatabasePlatform>>>,
+}
+
+impl AkitaConnection<'_> {
+    /// Take the connection out of the guard, e.g. to keep the owned `DatabasePlatform` `acquire` returned
+    /// before `with_schema`. The pinned connection of a `with_schema` handle can't be taken out, it has to be
+    /// switched back to the original schema before it goes back to the pool.
+    pub fn into_inner(mut self) -> Result<DatabasePlatform, AkitaError> {
+        if self.pinned.is_some() {
+            return Err(AkitaError::R2D2Error("[akita] the pinned connection of with_schema can't be taken out".to_string()));
+        }
+        self.conn.take().ok_or_else(|| AkitaError::R2D2Error("[akita] the connection was taken out".to_string()))
+    }
+}
+
+impl Deref for AkitaConnection<'_> {
+    type Target = DatabasePlatform;
+
+    fn deref(&self) -> &DatabasePlatform {
+        self.conn.as_ref().expect("the connection was taken out")
+    }
+}
+
+impl DerefMut for AkitaConnection<'_> {
+    fn deref_mut(&mut self) -> &mut DatabasePlatform {
+        self.conn.as_mut().expect("the connection was taken out")
+    }
+}
+
+impl Drop for AkitaConnection<'_> {
+    fn drop(&mut self) {
+        if let (Some(pinned), Some(conn)) = (self.pinned, self.conn.take()) {
+            // give it back even after a panic poisoned the slot, dropping it would return it to the pool unrestored
+            *pinned.lock().unwrap_or_else(|err| err.into_inner()) = Some(conn);
+        }
+    }
+}
+
+/// Switches the pinned connection of a `with_schema` handle back to the original schema once the block is over,
+/// also while unwinding from a panic of the closure. A connection which can't be switched back is killed, so
+/// the pool discards it instead of lending it out on the other schema.
+#[cfg(feature = "akita-mysql")]
+struct SchemaRestore {
+    handle: Akita,
+    original: String,
+}
+
+#[cfg(feature = "akita-mysql")]
+impl SchemaRestore {
+    fn restore(&mut self) -> Result<(), AkitaError> {
+        let conn = self.handle.pinned.as_ref().and_then(|slot| slot.lock().unwrap_or_else(|err| err.into_inner()).take());
+        match conn {
+            Some(mut conn) => conn.execute_drop(&format!("USE `{}`", self.original.replace('`', "``")), Params::Nil).map_err(|err| {
+                // r2d2 can't detach a connection, a dead one fails the `has_broken` check and is dropped by the pool
+                let _ = conn.execute_drop("KILL CONNECTION_ID()", Params::Nil);
+                err
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "akita-mysql")]
+impl Drop for SchemaRestore {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+/// The threads with an open transaction, so a thread starting a second one is refused while
+/// independent transactions of other threads sharing the handle go ahead.
+#[derive(Debug, Default)]
+pub(crate) struct OpenTransactions(Mutex<HashSet<ThreadId>>);
+
+impl OpenTransactions {
+    /// mark a transaction open on the current thread, failing when one is open already
+    pub(crate) fn enter(&self) -> Result<ThreadId, AkitaError> {
+        let thread = thread::current().id();
+        let mut open = self.0.lock().map_err(|err| AkitaError::R2D2Error(err.to_string()))?;
+        if !open.insert(thread) {
+            return Err(AkitaError::NestedTransaction("a transaction is already open on this thread".to_string()));
+        }
+        Ok(thread)
+    }
+
+    /// mark the transaction of the thread finished, it may be dropped on another thread
+    pub(crate) fn leave(&self, thread: ThreadId) {
+        if let Ok(mut open) = self.0.lock() {
+            open.remove(&thread);
+        }
+    }
 }
 
 #[allow(unused)]
@@ -34,7 +144,9 @@ impl Akita {
         let platform = Self::init_pool(&cfg)?;
         Ok(Self {
             pool: OnceCell::from(platform),
-            cfg
+            cfg,
+            transactions: OpenTransactions::default(),
+            pinned: None,
         })
     }
 
@@ -42,7 +154,9 @@ impl Akita {
         let platform = pool.get_pool()?;
         Ok(Self {
             pool: OnceCell::from(platform),
-            cfg: pool.config().clone()
+            cfg: pool.config().clone(),
+            transactions: OpenTransactions::default(),
+            pinned: None,
         })
     }
 
@@ -69,16 +183,77 @@ impl Akita {
         }
     }
 
+    /// Start a transaction, finished by `commit` or `rollback` or rolled back when dropped.
+    ///
+    /// Transactions don't nest: starting one while the same thread has another open on the handle fails with
+    /// `AkitaError::NestedTransaction` rather than being promoted to a savepoint, since a second `START TRANSACTION`
+    /// on MySQL silently commits the open one. Finish the outer transaction first.
     pub fn start_transaction(&self) -> Result<AkitaTransaction, AkitaError> {
-        let mut conn = self.acquire()?;
-        conn.start_transaction()?;
+        let thread = self.transactions.enter()?;
+        let started = self.acquire().and_then(|mut conn| conn.start_transaction());
+        if let Err(err) = started {
+            self.transactions.leave(thread);
+            return Err(err);
+        }
         Ok(AkitaTransaction {
             conn: &self,
+            thread,
             committed: false,
             rolled_back: false,
         })
     }
 
+    /// Run a block of operations against another schema of the server, e.g. for tenant-per-schema deployments,
+    /// leaving the global config untouched. On MySQL one connection is checked out of the pool and `USE schema`
+    /// is issued on it, the closure gets a handle pinned to that connection so every statement of the block sees
+    /// the switch. Afterward the connection is switched back to the schema of the config, or the one it was on,
+    /// and goes back to the pool, also when the closure failed or panicked. A connection without a schema to switch
+    /// back to is refused up front, and one which fails to switch back is discarded instead of returned to the pool.
+    /// As the handle has that one connection only, a second connection held at once inside the block, e.g. by
+    /// nesting `with_connection`, or using the handle from several threads fails to acquire.
+    /// SQLite has no schemas to switch to, the closure simply runs with this handle.
+    #[allow(unused_variables)]
+    pub fn with_schema<R, F>(&self, schema: &str, f: F) -> Result<R, AkitaError>
+        where
+            F: FnOnce(&Akita) -> Result<R, AkitaError>,
+    {
+        match self.cfg.platform() {
+            #[cfg(feature = "akita-mysql")]
+            Platform::Mysql => {
+                if schema.trim().is_empty() || schema.contains('`') {
+                    return Err(AkitaError::DataError(format!("invalid schema name: {}", schema)));
+                }
+                let mut conn = self.acquire()?;
+                let original = if self.cfg.db_name().is_empty() {
+                    let rows = conn.execute_result("SELECT DATABASE()", Params::Nil)?;
+                    Option::<String>::from_value_opt(rows.data.first().and_then(|row| row.first()).unwrap_or(&Value::Nil))?
+                } else {
+                    Some(self.cfg.db_name())
+                };
+                // without a schema to switch back to, the connection would go back to the pool on the other one
+                let original = match original {
+                    Some(original) if !original.is_empty() => original,
+                    _ => return Err(AkitaError::DataError("the connection has no schema to switch back to after with_schema".to_string())),
+                };
+                conn.execute_drop(&format!("USE `{}`", schema.trim()), Params::Nil)?;
+                let mut pinned = SchemaRestore {
+                    handle: Akita {
+                        pool: OnceCell::from(self.get_pool()?.clone()),
+                        cfg: self.cfg.clone(),
+                        transactions: OpenTransactions::default(),
+                        pinned: Some(Mutex::new(Some(conn.into_inner()?))),
+                    },
+                    original,
+                };
+                let result = f(&pinned.handle);
+                let restored = pinned.restore();
+                let result = result?;
+                restored.map(|_| result)
+            }
+            _ => f(self),
+        }
+    }
+
     /// get conn pool
     pub fn get_pool(&self) -> Result<&PlatformPool, AkitaError> {
         let p = self.pool.get();
@@ -88,16 +263,550 @@ impl Akita {
         return Ok(p.unwrap());
     }
 
-    /// get an DataBase Connection used for the next step
-    pub fn acquire(&self) -> Result<DatabasePlatform, AkitaError> {
+    /// get an DataBase Connection used for the next step, the pinned one of a `with_schema` handle.
+    /// The connection comes in an `AkitaConnection` guard which derefs to the `DatabasePlatform`, code that
+    /// needs the owned platform, as `acquire` returned it before `with_schema`, calls `into_inner` on it.
+    pub fn acquire(&self) -> Result<AkitaConnection<'_>, AkitaError> {
+        if let Some(pinned) = &self.pinned {
+            let conn = pinned.lock().map_err(|err| AkitaError::R2D2Error(err.to_string()))?.take()
+                .ok_or_else(|| AkitaError::R2D2Error("[akita] the pinned connection of with_schema is in use".to_string()))?;
+            return Ok(AkitaConnection { conn: Some(conn), pinned: Some(pinned) });
+        }
         let pool = self.get_pool()?;
-        let conn = pool.acquire()?;
-        match conn {
+        let start = metrics::start(&self.cfg);
+        let conn = pool.acquire();
+        metrics::record_acquire(&self.cfg, start, &conn);
+        let conn = match conn? {
             #[cfg(feature = "akita-mysql")]
-            PooledConnection::PooledMysql(pooled_mysql) => Ok(DatabasePlatform::Mysql(Box::new(MysqlDatabase::new(*pooled_mysql, self.cfg.to_owned())))),
+            PooledConnection::PooledMysql(pooled_mysql) => DatabasePlatform::Mysql(Box::new(MysqlDatabase::new(*pooled_mysql, self.cfg.to_owned()))),
             #[cfg(feature = "akita-sqlite")]
-            PooledConnection::PooledSqlite(pooled_sqlite) => Ok(DatabasePlatform::Sqlite(Box::new(SqliteDatabase::new(*pooled_sqlite, self.cfg.to_owned())))),
+            PooledConnection::PooledSqlite(pooled_sqlite) => DatabasePlatform::Sqlite(Box::new(SqliteDatabase::new(*pooled_sqlite, self.cfg.to_owned()))),
             _ => return Err(AkitaError::UnknownDatabase("database must be init.".to_string()))
+        };
+        Ok(AkitaConnection { conn: Some(conn), pinned: None })
+    }
+
+    /// Validate every pooled connection, e.g. as a readiness check after a failover: up to `max_size`
+    /// connections are checked out and pinged, the stale ones are evicted so the pool heals itself.
+    /// Each checkout waits at most the configured connection timeout, returns the number of healthy connections.
+    pub fn ping_all(&self) -> Result<u32, AkitaError> {
+        let pool = self.get_pool()?;
+        pool.ping_all(self.cfg.connection_timeout())
+    }
+
+    /// Lock the tables with `LOCK TABLES` for a maintenance job, the locks are released with `UNLOCK TABLES`
+    /// when the returned guard is dropped, also while unwinding from a panic.
+    /// MySQL table locks belong to a connection, so the guard holds one pooled connection and every statement
+    /// touching the locked tables must run on `guard.connection()`; a statement on any other connection blocks
+    /// or fails, and a locked session may not access tables it didn't lock. SQLite has no table locks,
+    /// `AkitaError::UnsupportedOperation` is returned, use a transaction instead.
+    #[allow(unused_variables)]
+    pub fn lock_tables(&self, locks: Vec<(&str, LockMode)>) -> Result<TableLockGuard, AkitaError> {
+        if locks.is_empty() {
+            return Err(AkitaError::DataError("tables to lock cannot be empty".to_string()))
+        }
+        let mut tables = Vec::with_capacity(locks.len());
+        for (table, mode) in locks.iter() {
+            let parts = table.trim().split('.').collect::<Vec<_>>();
+            if parts.iter().any(|part| part.is_empty() || !part.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+                return Err(AkitaError::MissingTable(format!("Invalid table name: {}", table)));
+            }
+            let mode = match mode { LockMode::Read => "READ", LockMode::Write => "WRITE" };
+            tables.push(format!("{} {}", parts.iter().map(|part| format!("`{}`", part)).collect::<Vec<_>>().join("."), mode));
+        }
+        let mut conn = self.acquire()?;
+        match *conn {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(_) => {
+                conn.execute_drop(&format!("LOCK TABLES {}", tables.join(", ")), Params::Nil)?;
+                Ok(TableLockGuard { conn, unlocked: false })
+            },
+            #[allow(unreachable_patterns)]
+            _ => Err(AkitaError::UnsupportedOperation("The platform does not support LOCK TABLES".to_string())),
+        }
+    }
+
+    /// Check out one connection and lend it to the closure, for a sequence of statements
+    /// which must run on the same connection outside a transaction, e.g. `SET @var` then a query using it.
+    /// The pool connection is tied up until the closure returns.
+    pub fn with_connection<R, F>(&self, f: F) -> Result<R, AkitaError>
+        where
+            F: FnOnce(&mut DatabasePlatform) -> Result<R, AkitaError>,
+    {
+        let mut conn = self.acquire()?;
+        f(&mut conn)
+    }
+
+    /// Find the record matching the lookup, or insert the entity and return the stored record.
+    /// The lookup and insert run in one transaction on one connection; when a concurrent caller inserts
+    /// the same unique key first, the unique violation is caught and the winner's record is selected instead.
+    pub fn get_or_insert<T>(&self, lookup: Wrapper, entity: &T) -> Result<T, AkitaError>
+        where
+            T: GetTableName + GetFields + FromValue + ToValue,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        let mut conn = self.acquire()?;
+        let (mut lookup, params) = lookup.bind_params(&conn);
+        let where_condition = lookup.get_sql_segment();
+        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
+        let sql = format!("SELECT {} FROM {} {} {}", T::select_columns(), &table.complete_name(), where_condition, conn.limit_clause(0, 1));
+        conn.start_transaction()?;
+        match find_or_insert(&mut conn, &sql, &params, entity) {
+            Ok(Some(record)) => {
+                conn.commit_transaction()?;
+                Ok(record)
+            }
+            Ok(None) => {
+                conn.rollback_transaction()?;
+                Err(AkitaError::DataError(format!("Table({}) the inserted record does not match the lookup", &table.name)))
+            }
+            Err(err) if err.is_unique_violation() => {
+                conn.rollback_transaction()?;
+                let rows = conn.execute_result(&sql, params)?;
+                rows.iter().next().map(|data| T::from_value(&data)).ok_or(err)
+            }
+            Err(err) => {
+                conn.rollback_transaction()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Copy the rows of `source_table` matching the wrapper into `target` on the server side with `INSERT ... SELECT`,
+    /// returns the number of inserted rows. The projection comes from the wrapper's `select` (`*` by default),
+    /// aligning it with the columns of the target table is up to the caller. The tables are plain or schema qualified names.
+    pub fn insert_select(&self, target: &str, source_wrapper: Wrapper, source_table: &str) -> Result<u64, AkitaError> {
+        if target.trim().is_empty() || source_table.trim().is_empty() {
+            return Err(AkitaError::MissingTable("Insert Error, Missing Table Name !".to_string()))
+        }
+        let (target, source_table) = (quote_table_str(target)?, quote_table_str(source_table)?);
+        let mut conn = self.acquire()?;
+        let (mut source_wrapper, params) = source_wrapper.bind_params(&conn);
+        let select_fields = source_wrapper.get_select_sql();
+        let where_condition = source_wrapper.get_sql_segment();
+        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
+        let sql = format!("INSERT INTO {} SELECT {} FROM {} {}", target, select_fields, source_table, where_condition);
+        conn.execute_result(&sql, params)?;
+        Ok(conn.affected_rows())
+    }
+
+    /// Upsert the rows of `source_table` matching the wrapper into `target` on the server side, `INSERT ... SELECT` where a row
+    /// hitting an existing primary or unique key updates `update_cols` of that row from the selected values instead.
+    /// Like `insert_select`, the wrapper's `select` (`*` by default) must line up with the columns of the target table.
+    /// MySQL appends `ON DUPLICATE KEY UPDATE col = VALUES(col)`, SQLite (3.35 or later) `ON CONFLICT DO UPDATE SET col = excluded.col`.
+    /// Returns the affected rows as counted by the platform, MySQL counts an updated row twice.
+    pub fn merge_from(&self, target: &str, source_table: &str, source_wrapper: Wrapper, update_cols: &[&str]) -> Result<u64, AkitaError> {
+        if target.trim().is_empty() || source_table.trim().is_empty() {
+            return Err(AkitaError::MissingTable("Merge Error, Missing Table Name !".to_string()))
+        }
+        if update_cols.is_empty() {
+            return Err(AkitaError::DataError("update columns cannot be empty".to_string()))
+        }
+        if let Some(col) = update_cols.iter().find(|col| col.is_empty() || !col.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+            return Err(AkitaError::InvalidField(format!("Invalid column name: {}", col)));
+        }
+        let (target, source_table) = (quote_table_str(target)?, quote_table_str(source_table)?);
+        let mut conn = self.acquire()?;
+        let (mut source_wrapper, params) = source_wrapper.bind_params(&conn);
+        let select_fields = source_wrapper.get_select_sql();
+        let where_condition = source_wrapper.get_sql_segment();
+        // sqlite needs a where clause to tell the upsert from a join constraint
+        let where_condition = if where_condition.trim().is_empty() { "WHERE 1 = 1".to_string() } else { format!("WHERE {}",where_condition) };
+        #[allow(unreachable_code)]
+        let on_conflict: String = match *conn {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(_) => format!("ON DUPLICATE KEY UPDATE {}", update_cols.iter().map(|col| format!("`{}` = VALUES(`{}`)", col, col)).collect::<Vec<_>>().join(", ")),
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => format!("ON CONFLICT DO UPDATE SET {}", update_cols.iter().map(|col| format!("`{}` = excluded.`{}`", col, col)).collect::<Vec<_>>().join(", ")),
+            _ => return Err(AkitaError::UnsupportedOperation("The platform does not support upsert".to_string())),
+        };
+        let sql = format!("INSERT INTO {} SELECT {} FROM {} {} {}", target, select_fields, source_table, where_condition, on_conflict);
+        conn.execute_result(&sql, params)?;
+        Ok(conn.affected_rows())
+    }
+
+    /// Set `col` of many rows to per-row values in one statement,
+    /// `UPDATE t SET col = CASE id WHEN ? THEN ? ... END WHERE id IN (...)`, e.g. to reorder items by their `sort` column.
+    /// Large inputs are split into chunks within the platform's placeholder limit, returns the number of affected rows.
+    pub fn update_values_by_id<T>(&self, col: &str, id_to_value: Vec<(Value, Value)>) -> Result<u64, AkitaError>
+        where
+            T: GetTableName + GetFields,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Update Error, Missing Table Name !".to_string()))
+        }
+        let columns = T::fields();
+        if !columns.iter().any(|field| field.exist && field.field_type == FieldType::TableField && field.name.eq(col)) {
+            return Err(AkitaError::InvalidField(format!("Table({}) Unknown Column: {}", &table.name, col)));
+        }
+        let id = columns.iter().find(|field| match field.field_type {
+            FieldType::TableId(_) => true,
+            FieldType::TableField => false,
+        }).ok_or_else(|| AkitaError::MissingIdent(format!("Table({}) Missing Ident...", &table.name)))?;
+        if id_to_value.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.acquire()?;
+        // each row binds its id twice (`WHEN ? THEN ?` and the `IN` list) and its value once
+        let chunk_size = (conn.max_placeholders() / 3).max(1);
+        let mut affected_rows = 0;
+        for chunk in id_to_value.chunks(chunk_size) {
+            let cases = chunk.iter().map(|_| "WHEN ? THEN ?").collect::<Vec<_>>().join(" ");
+            let ids = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+            let sql = format!("UPDATE {} SET `{}` = CASE `{}` {} END WHERE `{}` IN ({})", &table.complete_name(), col, &id.name, cases, &id.name, ids);
+            let mut values = Vec::with_capacity(chunk.len() * 3);
+            for (id_value, value) in chunk {
+                values.push(id_value.to_owned());
+                values.push(value.to_owned());
+            }
+            values.extend(chunk.iter().map(|(id_value, _)| id_value.to_owned()));
+            conn.execute_result(&sql, Params::Vector(values))?;
+            affected_rows += conn.affected_rows();
+        }
+        Ok(affected_rows)
+    }
+
+    /// Load many rows into the columns of the table, e.g. for a data pipeline moving millions of rows,
+    /// returns the number of rows loaded. The rows are read from the iterator and sent chunk by chunk.
+    ///
+    /// With `set_local_infile(true)` on MySQL each chunk is the in-memory file of a `LOAD DATA LOCAL INFILE`,
+    /// many times faster than inserting, which needs `local_infile = ON` on the server. Otherwise the chunks are
+    /// multi-row inserts within the placeholder limit of the platform. The chunks aren't wrapped in a transaction,
+    /// a failure leaves the earlier chunks loaded.
+    pub fn bulk_load<I>(&self, table: &str, columns: &[&str], rows: I) -> Result<u64, AkitaError>
+        where
+            I: Iterator<Item = Vec<Value>>,
+    {
+        let table = quote_table_str(table)?;
+        if columns.is_empty() || columns.iter().any(|col| col.is_empty() || !col.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+            return Err(AkitaError::InvalidField(format!("Invalid columns: {}", columns.join(", "))));
+        }
+        let mut conn = self.acquire()?;
+        #[allow(unreachable_patterns)]
+        let (infile, numbered) = match *conn {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(_) => (self.cfg.local_infile(), false),
+            _ => (false, true),
+        };
+        let chunk_size = if infile { BULK_INFILE_ROWS } else { (conn.max_placeholders() / columns.len()).max(1) };
+        let columns_sql = columns.iter().map(|col| format!("`{}`", col)).collect::<Vec<_>>().join(", ");
+        let mut rows = rows;
+        let mut loaded = 0;
+        loop {
+            let chunk = rows.by_ref().take(chunk_size).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
+            if let Some(row) = chunk.iter().find(|row| row.len() != columns.len()) {
+                return Err(AkitaError::DataError(format!("a row of {} values for {} columns", row.len(), columns.len())));
+            }
+            loaded += if infile {
+                let mut data = Vec::new();
+                chunk.iter().for_each(|row| infile_row(row, &mut data));
+                let sql = format!("LOAD DATA LOCAL INFILE 'akita_bulk_load' INTO TABLE {} CHARACTER SET utf8mb4 ({})", table, columns_sql);
+                #[allow(unreachable_patterns)]
+                match *conn {
+                    #[cfg(feature = "akita-mysql")]
+                    DatabasePlatform::Mysql(ref mut db) => db.load_local_infile(&sql, data)?,
+                    _ => return Err(AkitaError::UnsupportedOperation("LOAD DATA is MySQL only".to_string())),
+                }
+            } else {
+                let sql = build_bulk_insert_clause(&table, columns, chunk.len(), numbered);
+                conn.execute_result(&sql, Params::Vector(chunk.concat()))?;
+                conn.affected_rows()
+            };
+        }
+        Ok(loaded)
+    }
+
+    /// The last statement executed on the current thread with a summary of its params, e.g. to assert the
+    /// generated sql in a test. Needs the `akita-debug` feature, otherwise nothing is recorded and it is always `None`.
+    pub fn last_query(&self) -> Option<String> {
+        #[cfg(feature = "akita-debug")]
+        return crate::debug::last_query();
+        #[cfg(not(feature = "akita-debug"))]
+        None
+    }
+
+    /// Visit the rows of a query one at a time without buffering the whole result, e.g. for a long export with
+    /// a progress bar. `f` gets the running index and the row as an object value keyed by the column names,
+    /// an error returned from it stops the iteration and is passed on. Returns the number of rows visited.
+    pub fn for_each_row<S, P, F>(&self, sql: S, params: P, mut f: F) -> Result<usize, AkitaError>
+        where
+            S: Into<String>,
+            P: Into<Params>,
+            F: FnMut(usize, &Value) -> Result<(), AkitaError>,
+    {
+        let sql: String = sql.into();
+        let mut conn = self.acquire()?;
+        conn.for_each_row(&sql, params.into(), &mut f)
+    }
+
+    /// Stream a BLOB to the writer without building the whole result set, e.g. to save a large file to disk.
+    /// The statement must select exactly one column, only the first row is read and the rest are ignored.
+    /// A NULL value or an empty result writes nothing, returns the number of bytes written.
+    /// SQLite hands out its own buffer of the value, the MySQL driver still reads the whole row first.
+    pub fn stream_blob<S: Into<String>, P: Into<Params>>(&self, sql: S, params: P, writer: &mut dyn Write) -> Result<u64, AkitaError> {
+        let sql: String = sql.into();
+        let mut conn = self.acquire()?;
+        conn.stream_blob(&sql, params.into(), writer)
+    }
+
+    /// Run a statement which can be aborted from another thread, e.g. when the client of a request disconnects.
+    /// Once the token is cancelled, `KILL QUERY` is issued for the statement's connection from a side connection,
+    /// so the pool needs a spare connection for it. Only MySQL supports it, a cancelled statement fails with `QueryCancelled`.
+    pub fn exec_cancellable<S: Into<String>, P: Into<Params>>(&self, sql: S, params: P, cancel: CancelToken) -> Result<Rows, AkitaError> {
+        let sql = sql.into();
+        if cancel.is_cancelled() {
+            return Err(AkitaError::QueryCancelled(sql));
+        }
+        let mut conn = self.acquire()?;
+        let connection_id = conn.connection_id()
+            .ok_or_else(|| AkitaError::UnsupportedOperation("The platform does not support cancelling a query".to_string()))?;
+        let pool = self.get_pool()?.clone();
+        let cfg = self.cfg.clone();
+        let done = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let (done, cancel) = (done.clone(), cancel.clone());
+            std::thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    if cancel.is_cancelled() {
+                        if let Ok(mut side) = pool.database(&cfg) {
+                            let _ = side.execute_result(&format!("KILL QUERY {}", connection_id), Params::Nil);
+                        }
+                        return;
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+            })
+        };
+        let result = conn.execute_result(&sql, params.into());
+        done.store(true, Ordering::SeqCst);
+        let _ = watcher.join();
+        match result {
+            Err(_) if cancel.is_cancelled() => Err(AkitaError::QueryCancelled(sql)),
+            result => result,
+        }
+    }
+
+    /// Run a sql script such as a migration or seed file, statement by statement in one transaction.
+    /// The script is split on `;` outside quotes and comments, `DELIMITER` lines are honored for stored routines.
+    /// Note MySQL commits implicitly on DDL, so a failing script may leave its earlier DDL applied.
+    /// The error names the number and the text of the failed statement.
+    pub fn execute_file<P: AsRef<Path>>(&self, path: P) -> Result<(), AkitaError> {
+        let path = path.as_ref();
+        let script = std::fs::read_to_string(path).map_err(|e| AkitaError::DataError(format!("Failed to read sql file {}: {}", path.display(), e)))?;
+        let mut conn = self.acquire()?;
+        conn.start_transaction()?;
+        for (i, statement) in split_sql_statements(&script).iter().enumerate() {
+            if let Err(err) = conn.execute_result(statement, Params::Nil) {
+                conn.rollback_transaction()?;
+                return Err(AkitaError::ExcuteSqlError(format!("statement {} of {} failed: {}", i + 1, path.display(), err), statement.to_string()));
+            }
+        }
+        conn.commit_transaction()
+    }
+
+    /// Run many statements as a bulk load, committing every `commit_every` operations and once more at the end,
+    /// which bounds the size of each transaction instead of holding one giant transaction.
+    /// On error the current uncommitted chunk is rolled back and `BatchAborted` reports how many operations were committed.
+    /// Returns the number of operations run.
+    pub fn batch_exec_in_transaction<I, S, P>(&self, operations: I, commit_every: usize) -> Result<u64, AkitaError>
+        where
+            I: IntoIterator<Item = (S, P)>,
+            S: Into<String>,
+            P: Into<Params>,
+    {
+        let commit_every = commit_every.max(1) as u64;
+        let mut conn = self.acquire()?;
+        let (mut committed, mut pending) = (0u64, 0u64);
+        let mut operations = operations.into_iter().peekable();
+        while let Some((sql, params)) = operations.next() {
+            if pending == 0 {
+                conn.start_transaction().map_err(|error| AkitaError::BatchAborted { committed, error: Box::new(error) })?;
+            }
+            let sql: String = sql.into();
+            let mut result = conn.execute_result(&sql, params.into()).map(|_| ());
+            pending += 1;
+            if result.is_ok() && (pending == commit_every || operations.peek().is_none()) {
+                result = conn.commit_transaction();
+                if result.is_ok() {
+                    committed += pending;
+                    pending = 0;
+                }
+            }
+            if let Err(error) = result {
+                let _ = conn.rollback_transaction();
+                return Err(AkitaError::BatchAborted { committed, error: Box::new(error) });
+            }
+        }
+        Ok(committed)
+    }
+
+    /// Create the table of the entity unless it exists, see `build_create_table_clause` for the column types.
+    #[cfg(feature = "akita-ddl")]
+    pub fn create_table<T>(&self) -> Result<(), AkitaError>
+        where
+            T: GetTableName + GetFields,
+    {
+        if T::table_name().complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Create Error, Missing Table Name !".to_string()))
+        }
+        let mut conn = self.acquire()?;
+        conn.execute_result(&build_create_table_clause::<T>(), Params::Nil)?;
+        Ok(())
+    }
+
+    /// Drop the table of the entity, with `if_exists` a missing table is no error, e.g. for a test teardown.
+    #[cfg(feature = "akita-ddl")]
+    pub fn drop_table<T>(&self, if_exists: bool) -> Result<(), AkitaError>
+        where
+            T: GetTableName,
+    {
+        let table = quote_table_name(&T::table_name())?;
+        let mut conn = self.acquire()?;
+        conn.execute_result(&format!("DROP TABLE {}{}", if if_exists { "IF EXISTS " } else { "" }, table), Params::Nil)?;
+        Ok(())
+    }
+
+    /// Rename a table, both names may be qualified with the schema, e.g. `db.t_user`.
+    /// MySQL runs `RENAME TABLE`, which can move the table to another schema as well;
+    /// SQLite runs `ALTER TABLE .. RENAME TO`, which keeps the table in its schema.
+    #[cfg(feature = "akita-ddl")]
+    pub fn rename_table(&self, from: &str, to: &str) -> Result<(), AkitaError> {
+        let (from, to) = (TableName::from(from.trim()), TableName::from(to.trim()));
+        let (quoted_from, quoted_to) = (quote_table_name(&from)?, quote_table_name(&to)?);
+        let mut conn = self.acquire()?;
+        #[allow(unreachable_code)]
+        let sql: String = match *conn {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(_) => format!("RENAME TABLE {} TO {}", quoted_from, quoted_to),
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => {
+                if to.schema.is_some() && to.schema != from.schema {
+                    return Err(AkitaError::UnsupportedOperation(format!("SQLite can not move the table {} to another schema", from.complete_name())));
+                }
+                format!("ALTER TABLE {} RENAME TO `{}`", quoted_from, to.name)
+            }
+            _ => return Err(AkitaError::UnknownDatabase("database must be init.".to_string())),
+        };
+        conn.execute_result(&sql, Params::Nil)?;
+        Ok(())
+    }
+
+    /// Add the column of a field of the entity, typed like `create_table` does, for code-first migrations.
+    #[cfg(feature = "akita-ddl")]
+    pub fn add_column<T>(&self, field_name: &str) -> Result<(), AkitaError>
+        where
+            T: GetTableName + GetFields,
+    {
+        let sql = build_add_column_clause::<T>(field_name)?;
+        let mut conn = self.acquire()?;
+        conn.execute_result(&sql, Params::Nil)?;
+        Ok(())
+    }
+
+    /// Drop a column of a table, the table name may be qualified with the schema.
+    /// SQLite only drops columns since 3.35, an older library fails with `UnsupportedOperation`
+    /// rather than rebuilding the table behind the caller's back.
+    #[cfg(feature = "akita-ddl")]
+    pub fn drop_column(&self, table: &str, col: &str) -> Result<(), AkitaError> {
+        let table = quote_table_name(&TableName::from(table.trim()))?;
+        if col.is_empty() || !col.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(AkitaError::InvalidField(format!("Invalid column name: {}", col)));
+        }
+        let mut conn = self.acquire()?;
+        #[cfg(feature = "akita-sqlite")]
+        if let DatabasePlatform::Sqlite(_) = *conn {
+            let version = conn.execute_result("SELECT sqlite_version() AS version", Params::Nil)?.iter().next()
+                .and_then(|data| data.get_obj::<String>("version").ok()).unwrap_or_default();
+            let parts = version.split('.').map(|part| part.parse::<u32>().unwrap_or_default()).collect::<Vec<_>>();
+            if (parts.first().copied().unwrap_or_default(), parts.get(1).copied().unwrap_or_default()) < (3, 35) {
+                return Err(AkitaError::UnsupportedOperation(format!("SQLite {} can not drop a column, 3.35 or later is needed", version)));
+            }
+        }
+        conn.execute_result(&format!("ALTER TABLE {} DROP COLUMN `{}`", table, col), Params::Nil)?;
+        Ok(())
+    }
+
+    /// Check if the table exists, the name may be qualified with the schema, e.g. `db.table`.
+    /// An unqualified name is looked up in the current database.
+    pub fn exists_table(&self, name: &str) -> Result<bool, AkitaError> {
+        if name.trim().is_empty() || name.split('.').count() > 2 {
+            return Err(AkitaError::MissingTable(format!("Invalid table name: {}", name)));
+        }
+        let mut conn = self.acquire()?;
+        conn.exist_table(&TableName::from(name.trim()))
+    }
+
+    /// Whether a record with the primary key exists, cheaper than `select_by_id` since no columns are fetched,
+    /// e.g. to validate a foreign reference. The key is the `#[table_id]` column like in `select_by_id`.
+    pub fn exists_by_id<T, I>(&self, id: I) -> Result<bool, AkitaError>
+        where
+            T: GetTableName + GetFields,
+            I: ToValue
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        let field = T::fields().into_iter().find(|field| match field.field_type {
+            FieldType::TableId(_) => true,
+            FieldType::TableField => false,
+        }).ok_or_else(|| AkitaError::MissingIdent(format!("Table({}) Missing Ident...", &table.name)))?;
+        let mut conn = self.acquire()?;
+        let sql = format!("SELECT 1 FROM {} WHERE `{}` = ? {}", &table.complete_name(), &field.name, conn.limit_clause(0, 1));
+        let rows = conn.execute_result(&sql, (id.to_value(),).into())?;
+        Ok(!rows.data.is_empty())
+    }
+
+    /// A fast approximate row count from the table statistics, for pagination totals and dashboards where `count`
+    /// would scan a huge table. MySQL reads `information_schema.TABLES.TABLE_ROWS`, which InnoDB only samples and may be
+    /// stale or off by a large margin; SQLite reads `sqlite_stat1`, only filled by `ANALYZE`.
+    /// Without statistics it falls back to an exact `count(1)`.
+    pub fn row_count_estimate<T>(&self) -> Result<u64, AkitaError>
+        where
+            T: GetTableName,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        let mut conn = self.acquire()?;
+        #[allow(unreachable_code)]
+        let estimate: Option<u64> = match *conn {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(_) => {
+                let (sql, params): (&str, Params) = match table.schema {
+                    Some(ref schema) => ("SELECT TABLE_ROWS FROM information_schema.TABLES WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ?", (schema, &table.name).into()),
+                    None => ("SELECT TABLE_ROWS FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?", (&table.name,).into()),
+                };
+                conn.execute_result(sql, params)?.iter().next()
+                    .and_then(|data| data.get_obj_opt::<u64>("TABLE_ROWS").ok().flatten())
+            }
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => {
+                // sqlite_stat1 only exists once ANALYZE ran, the first number of `stat` is the row count
+                let sql = match table.schema {
+                    Some(ref schema) => format!("SELECT stat FROM `{}`.sqlite_stat1 WHERE tbl = ? LIMIT 1", schema),
+                    None => "SELECT stat FROM sqlite_stat1 WHERE tbl = ? LIMIT 1".to_string(),
+                };
+                conn.execute_result(&sql, (&table.name,).into()).ok()
+                    .and_then(|rows| rows.iter().next().and_then(|data| data.get_obj_opt::<String>("stat").ok().flatten()))
+                    .and_then(|stat| stat.split_whitespace().next().and_then(|rows| rows.parse::<u64>().ok()))
+            }
+            _ => return Err(AkitaError::UnknownDatabase("database must be init.".to_string()))
+        };
+        match estimate {
+            Some(estimate) => Ok(estimate),
+            None => {
+                let rows = conn.execute_result(&format!("select count(1) as count from {}", &table.complete_name()), Params::Nil)?;
+                Ok(rows.iter().next().map(|data| u64::from_value(&data)).unwrap_or_default())
+            }
         }
     }
 
@@ -106,10 +815,83 @@ impl Akita {
     }
 }
 
+/// A shared flag to abort a running statement, see `Akita::exec_cancellable`.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Abort the statement running with this token, or the next one started with it.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The mode of a table lock, see `Akita::lock_tables`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockMode {
+    /// other sessions may read the table but not write it
+    Read,
+    /// other sessions may neither read nor write the table
+    Write,
+}
+
+/// The connection holding the locks of `Akita::lock_tables`, which issues `UNLOCK TABLES` when dropped.
+pub struct TableLockGuard {
+    conn: DatabasePlatform,
+    unlocked: bool,
+}
+
+impl TableLockGuard {
+    /// The connection holding the locks, the statements on the locked tables must run on it.
+    pub fn connection(&mut self) -> &mut DatabasePlatform {
+        &mut self.conn
+    }
+
+    /// Release the locks now and report a failure, which dropping the guard can only log.
+    pub fn unlock(mut self) -> Result<(), AkitaError> {
+        self.unlocked = true;
+        self.conn.execute_drop("UNLOCK TABLES", Params::Nil)
+    }
+}
+
+impl Drop for TableLockGuard {
+    fn drop(&mut self) {
+        if !self.unlocked {
+            if let Err(_err) = self.conn.execute_drop("UNLOCK TABLES", Params::Nil) {
+                #[cfg(feature = "akita-logging")]
+                log::error!("[Akita]: failed to release the table locks: {}", _err);
+                #[cfg(feature = "akita-tracing")]
+                tracing::error!("[Akita]: failed to release the table locks: {}", _err);
+            }
+        }
+    }
+}
+
+/// select the first record, inserting the entity first when there is none
+fn find_or_insert<T>(conn: &mut DatabasePlatform, sql: &str, params: &Params, entity: &T) -> Result<Option<T>, AkitaError>
+    where
+        T: GetTableName + GetFields + FromValue + ToValue,
+{
+    if let Some(data) = conn.execute_result(sql, params.to_owned())?.iter().next() {
+        return Ok(Some(T::from_value(&data)));
+    }
+    let insert_sql = build_insert_clause(conn, &[entity]);
+    conn.execute_result(&insert_sql, build_insert_values(&[entity]).into())?;
+    Ok(conn.execute_result(sql, params.to_owned())?.iter().next().map(|data| T::from_value(&data)))
+}
+
 #[allow(unused)]
 impl AkitaMapper for Akita {
     /// Get all the table of records
-    fn list<T>(&self, mut wrapper:Wrapper) -> Result<Vec<T>, AkitaError>
+    fn list<T>(&self, wrapper:Wrapper) -> Result<Vec<T>, AkitaError>
         where
             T: GetTableName + GetFields + FromValue,
 
@@ -118,23 +900,9 @@ impl AkitaMapper for Akita {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let columns = T::fields();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let select_fields = wrapper.get_select_sql();
-        let enumerated_columns = if select_fields.eq("*") {
-            enumerated_columns
-        } else {
-            select_fields
-        };
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let sql = format!("SELECT {} FROM {} {}", &enumerated_columns, &table.complete_name(),where_condition);
         let mut conn = self.acquire()?;
-        let rows = conn.execute_result(&sql, Params::Nil)?;
+        let (sql, params) = list_query::<T>(&conn, wrapper);
+        let rows = conn.execute_result(&sql, params)?;
         let mut entities = vec![];
         for data in rows.iter() {
             let entity = T::from_value(&data);
@@ -144,7 +912,7 @@ impl AkitaMapper for Akita {
     }
 
     /// Get one the table of records
-    fn select_one<T>(&self, mut wrapper:Wrapper) -> Result<Option<T>, AkitaError>
+    fn select_one<T>(&self, wrapper:Wrapper) -> Result<Option<T>, AkitaError>
         where
             T: GetTableName + GetFields + FromValue,
     {
@@ -152,23 +920,17 @@ impl AkitaMapper for Akita {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let columns = T::fields();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
+        let mut conn = self.acquire()?;
+        let (mut wrapper, params) = wrapper.bind_params(&conn);
         let select_fields = wrapper.get_select_sql();
         let enumerated_columns = if select_fields.eq("*") {
-            enumerated_columns
+            T::select_columns().to_string()
         } else {
             select_fields
         };
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let sql = format!("SELECT {} FROM {} {}", &enumerated_columns, &table.complete_name(), where_condition);
-        let mut conn = self.acquire()?;
-        let rows = conn.execute_result(&sql, Params::Nil)?;
+        let (from, where_condition) = wrapper.get_from_sql(&table.complete_name());
+        let sql = format!("SELECT {} FROM {} {} {}", &enumerated_columns, from, where_condition, conn.limit_clause(0, 1));
+        let rows = conn.execute_result(&sql, params)?;
         Ok(rows.iter().next().map(|data| T::from_value(&data)))
     }
 
@@ -183,24 +945,13 @@ impl AkitaMapper for Akita {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
         let columns = T::fields();
-        let col_len = columns.len();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
+        let enumerated_columns = T::select_columns();
         let mut conn = self.acquire()?;
         if let Some(field) = columns.iter().find(| field| match field.field_type {
             FieldType::TableId(_) => true,
             FieldType::TableField => false,
         }) {
-            let sql = match conn {
-                #[cfg(feature = "akita-mysql")]
-                DatabasePlatform::Mysql(_) => format!("SELECT {} FROM {} WHERE `{}` = ? limit 1", &enumerated_columns, &table.complete_name(), &field.name),
-                #[cfg(feature = "akita-sqlite")]
-                DatabasePlatform::Sqlite(_) => format!("SELECT {} FROM {} WHERE `{}` = ${} limit 1", &enumerated_columns, &table.complete_name(), &field.name, col_len + 1),
-                _ => format!("SELECT {} FROM {} WHERE `{}` = ${} limit 1", &enumerated_columns, &table.complete_name(), &field.name, col_len + 1),
-            };
+            let sql = format!("SELECT {} FROM {} WHERE `{}` = ? {}", &enumerated_columns, &table.complete_name(), &field.name, conn.limit_clause(0, 1));
 
             let rows = conn.execute_result(&sql, (id.to_value(),).into())?;
             Ok(rows.iter().next().map(|data| T::from_value(&data)))
@@ -210,7 +961,7 @@ impl AkitaMapper for Akita {
     }
 
     /// Get table of records with page
-    fn page<T>(&self, page: usize, size: usize, mut wrapper:Wrapper) -> Result<IPage<T>, AkitaError>
+    fn page<T>(&self, page: usize, size: usize, wrapper:Wrapper) -> Result<IPage<T>, AkitaError>
         where
             T: GetTableName + GetFields + FromValue,
 
@@ -219,27 +970,13 @@ impl AkitaMapper for Akita {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let columns = T::fields();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let select_fields = wrapper.get_select_sql();
-        let enumerated_columns = if select_fields.eq("*") {
-            enumerated_columns
-        } else {
-            select_fields
-        };
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let count_sql = format!("select count(1) as count from {} {}", &table.complete_name(), where_condition);
-        let count: i64 = self.exec_first(&count_sql, ())?;
+        let mut conn = self.acquire()?;
+        let (count_sql, sql, params) = page_query::<T>(&conn, &self.cfg, wrapper);
+        let count = conn.execute_result(&count_sql, params.to_owned())?.iter().next().map(|data| i64::from_value(&data)).unwrap_or_default();
         let mut page = IPage::new(page, size ,count as usize, vec![]);
         if page.total > 0 {
-            let sql = format!("SELECT {} FROM {} {} limit {}, {}", &enumerated_columns, &table.complete_name(), where_condition,page.offset(),  page.size);
-            let mut conn = self.acquire()?;
-            let rows = conn.execute_result(&sql, Params::Nil)?;
+            let sql = format!("{} {}", sql, conn.limit_clause(page.offset(), page.size));
+            let rows = conn.execute_result(&sql, params)?;
             let mut entities = vec![];
             for dao in rows.iter() {
                 let entity = T::from_value(&dao);
@@ -250,6 +987,49 @@ impl AkitaMapper for Akita {
         Ok(page)
     }
 
+    /// Get table of records with page, without running the count query.
+    fn page_records<T>(&self, page: usize, size: usize, wrapper: Wrapper) -> Result<Vec<T>, AkitaError>
+        where
+            T: GetTableName + GetFields + FromValue,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        let mut conn = self.acquire()?;
+        page_records_on(&mut conn, &self.cfg, page, size, wrapper)
+    }
+
+    /// Get all the table of records as a shared slice.
+    fn list_arc<T>(&self, wrapper: Wrapper) -> Result<Arc<[T]>, AkitaError>
+        where
+            T: GetTableName + GetFields + FromValue,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        let mut conn = self.acquire()?;
+        list_arc_on(&mut conn, wrapper)
+    }
+
+    /// Count the records per value of `group_col`.
+    fn count_group_by<T, K>(&self, group_col: &str, wrapper: Wrapper) -> Result<HashMap<K, usize>, AkitaError>
+        where
+            T: GetTableName + GetFields,
+            K: FromValue + Eq + Hash,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        if !T::fields().iter().any(|f| f.exist && f.name.eq(group_col)) {
+            return Err(AkitaError::InvalidField(format!("Table({}) Unknown Column: {}", &table.name, group_col)));
+        }
+        let mut conn = self.acquire()?;
+        count_group_by_on::<T, K>(&mut conn, group_col, wrapper)
+    }
+
     /// Get the total count of records
     fn count<T>(&self, mut wrapper:Wrapper) -> Result<usize, AkitaError>
         where
@@ -259,14 +1039,12 @@ impl AkitaMapper for Akita {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let sql = format!(
-            "SELECT COUNT(1) AS count FROM {} {}",
-            table.complete_name(),
-            where_condition
-        );
-        self.exec_first(&sql, ())
+        let mut conn = self.acquire()?;
+        let (mut wrapper, params) = wrapper.bind_params(&conn);
+        let (from, where_condition) = wrapper.get_from_sql(&table.complete_name());
+        let sql = wrapper.get_count_sql(&from, &where_condition);
+        let count = conn.execute_result(&sql, params)?.iter().next().map(|data| i64::from_value(&data)).unwrap_or_default();
+        Ok(count as usize)
     }
 
     /// Remove the records by wrapper.
@@ -278,11 +1056,12 @@ impl AkitaMapper for Akita {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
+        let mut conn = self.acquire()?;
+        let (mut wrapper, params) = wrapper.bind_params(&conn);
         let where_condition = wrapper.get_sql_segment();
         let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
         let sql = format!("delete from {} {}", &table.complete_name(), where_condition);
-        let mut conn = self.acquire()?;
-        let _rows = conn.execute_result(&sql, Params::Nil)?;
+        let _rows = conn.execute_result(&sql, params)?;
         Ok(conn.affected_rows())
     }
 
@@ -302,7 +1081,7 @@ impl AkitaMapper for Akita {
             FieldType::TableId(_) => true,
             FieldType::TableField => false,
         }) {
-            let sql = match conn {
+            let sql = match *conn {
                 #[cfg(feature = "akita-mysql")]
                 DatabasePlatform::Mysql(_) => format!("delete from {} where `{}` = ?", &table.name, &field.name),
                 #[cfg(feature = "akita-sqlite")]
@@ -326,26 +1105,11 @@ impl AkitaMapper for Akita {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let cols = T::fields();
-        let mut conn = self.acquire()?;
-        let col_len = cols.len();
-        if let Some(field) = cols.iter().find(| field| match field.field_type {
-            FieldType::TableId(_) => true,
-            FieldType::TableField => false,
-        }) {
-            let sql = match conn {
-                #[cfg(feature = "akita-mysql")]
-                DatabasePlatform::Mysql(_) => format!("delete from {} where `{}` in (?)", &table.name, &field.name),
-                #[cfg(feature = "akita-sqlite")]
-                DatabasePlatform::Sqlite(_) => format!("delete from {} where `{}` in (${})", &table.name, &field.name, col_len + 1),
-                _ => format!("delete from {} where `{}` = ${}", &table.name, &field.name, col_len + 1),
-            };
-            let ids = ids.iter().map(|v| v.to_value().to_string()).collect::<Vec<String>>().join(",");
-            let _rows = conn.execute_result(&sql, (ids,).into())?;
-            Ok(conn.affected_rows())
-        } else {
-            Err(AkitaError::MissingIdent(format!("Table({}) Missing Ident...", &table.name)))
+        if ids.is_empty() {
+            return Ok(0);
         }
+        let mut conn = self.acquire()?;
+        remove_by_ids_on::<T, I>(&mut conn, ids)
     }
 
 
@@ -359,6 +1123,9 @@ impl AkitaMapper for Akita {
         }
         let mut conn = self.acquire()?;
         let columns = T::fields();
+        // the placeholders of the conditions follow those of the entity values
+        let first = if wrapper.fields_set.is_empty() && wrapper.get_set_sql().is_none() { columns.iter().filter(|col| col.exist && col.field_type == FieldType::TableField).count() } else { 0 };
+        let (mut wrapper, params) = wrapper.bind_params_after(&conn, first);
         let mut sql = build_update_clause(&conn, entity, &mut wrapper);
         let update_fields = wrapper.fields_set.to_owned();
         let is_set = wrapper.get_set_sql().is_none();
@@ -391,10 +1158,10 @@ impl AkitaMapper for Akita {
                     None => values.push(Value::Nil),
                 }
             }
-
+            values.extend(params);
             let _rows = conn.execute_result(&sql, values.into())?;
         } else {
-            let _rows = conn.execute_result(&sql, Params::Nil)?;
+            let _rows = conn.execute_result(&sql, params.into())?;
         }
         Ok(conn.affected_rows())
     }
@@ -420,7 +1187,7 @@ impl AkitaMapper for Akita {
                 .enumerate()
                 .map(|(x, col)| {
                     #[allow(unreachable_patterns)]
-                    match conn {
+                    match *conn {
                         #[cfg(feature = "akita-mysql")]
                         DatabasePlatform::Mysql(_) => format!("`{}` = ?", &col.name),
                         #[cfg(feature = "akita-sqlite")]
@@ -430,7 +1197,7 @@ impl AkitaMapper for Akita {
                 })
                 .collect::<Vec<_>>()
                 .join(", ");
-            let sql = match conn {
+            let sql = match *conn {
                 #[cfg(feature = "akita-mysql")]
                 DatabasePlatform::Mysql(_) => format!("update {} set {} where `{}` = ?", &table.name, &set_fields, &field.name),
                 #[cfg(feature = "akita-sqlite")]
@@ -476,39 +1243,16 @@ impl AkitaMapper for Akita {
     }
 
     #[allow(unused_variables)]
-    fn save_batch<T>(&self, entities: &[&T]) -> Result<(), AkitaError>
+    fn save_batch<T>(&self, entities: &[&T]) -> Result<u64, AkitaError>
         where
             T: GetTableName + GetFields + ToValue
     {
-        let columns = T::fields();
         let mut conn = self.acquire()?;
         let sql = build_insert_clause(&conn, entities);
 
-        let mut values: Vec<Value> = Vec::with_capacity(entities.len() * columns.len());
-        for entity in entities.iter() {
-            for col in columns.iter() {
-                let data = entity.to_value();
-                let mut value = data.get_obj_value(&col.name);
-                match &col.fill {
-                    None => {}
-                    Some(v) => {
-                        match v.mode.as_ref() {
-                            "insert" | "default" => {
-                                value = v.value.as_ref();
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                match value {
-                    Some(value) => values.push(value.clone()),
-                    None => values.push(Value::Nil),
-                }
-            }
-        }
-        let bvalues: Vec<&Value> = values.iter().collect();
+        let values = build_insert_values(entities);
         conn.execute_result(&sql,values.into())?;
-        Ok(())
+        Ok(conn.affected_rows())
     }
 
     /// called multiple times when using database platform that doesn;t support multiple value
@@ -517,33 +1261,13 @@ impl AkitaMapper for Akita {
             T: GetTableName + GetFields + ToValue,
             I: FromValue,
     {
-        let columns = T::fields();
         let mut conn = self.acquire()?;
         let sql = build_insert_clause(&conn, &[entity]);
-        let data = entity.to_value();
-        let mut values: Vec<Value> = Vec::with_capacity(columns.len());
-        for col in columns.iter() {
-            let mut value = data.get_obj_value(&col.name);
-            match &col.fill {
-                None => {}
-                Some(v) => {
-                    match v.mode.as_ref() {
-                        "insert" | "default" => {
-                            value = v.value.as_ref();
-                        }
-                        _=> {}
-                    }
-                }
-            }
-            match value {
-                Some(value) => values.push(value.clone()),
-                None => values.push(Value::Nil),
-            }
-        }
-        let _bvalues: Vec<&Value> = values.iter().collect();
+        let values = build_insert_values(&[entity]);
 
         conn.execute_result(&sql,values.into())?;
-        let _rows: Rows = match conn {
+        warn_id_overflow::<T>(&self.cfg, conn.last_insert_id());
+        let _rows: Rows = match *conn {
             #[cfg(feature = "akita-mysql")]
             DatabasePlatform::Mysql(_) => conn.execute_result("SELECT LAST_INSERT_ID();", Params::Nil)?,
             #[cfg(feature = "akita-sqlite")]
@@ -554,6 +1278,53 @@ impl AkitaMapper for Akita {
         Ok(last_insert_id)
     }
 
+    /// Save the entity and return the generated value of the named column with `RETURNING`,
+    /// for tables whose key is not the default auto-increment one.
+    fn save_returning_column<T, I>(&self, entity: &T, id_col: &str) -> Result<I, AkitaError>
+        where
+            T: GetTableName + GetFields + ToValue,
+            I: FromValue,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        if !T::fields().iter().any(|f| f.exist && f.name.eq(id_col)) {
+            return Err(AkitaError::InvalidField(format!("Table({}) Unknown Column: {}", &table.name, id_col)));
+        }
+        let mut conn = self.acquire()?;
+        #[allow(unreachable_code)]
+        let sql: String = match *conn {
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => format!("{} RETURNING `{}`", build_insert_clause(&conn, &[entity]), id_col),
+            _ => return Err(AkitaError::UnsupportedOperation(format!("The platform can not return the column({}) of the inserted record", id_col))),
+        };
+        let rows = conn.execute_result(&sql, build_insert_values(&[entity]).into())?;
+        match rows.iter().next() {
+            Some(data) => Ok(I::from_value(&data)),
+            None => Err(AkitaError::DataError(format!("Column({}) was not returned", id_col))),
+        }
+    }
+
+    /// Insert or update a batch of records in multi-row statements
+    fn save_or_update_batch_on_conflict<T>(&self, entities: &[&T], update_cols: &[&str]) -> Result<u64, AkitaError>
+        where
+            T: GetTableName + GetFields + ToValue,
+    {
+        let mut conn = self.acquire()?;
+        save_batch_on_conflict(&mut conn, entities, update_cols)
+    }
+
+    fn save_replace<T>(&self, entity: &T) -> Result<u64, AkitaError>
+        where
+            T: GetTableName + GetFields + ToValue,
+    {
+        let mut conn = self.acquire()?;
+        let sql = build_replace_clause(&conn, &[entity])?;
+        conn.execute_result(&sql, build_insert_values(&[entity]).into())?;
+        Ok(conn.affected_rows())
+    }
+
     /// save or update
     fn save_or_update<T, I>(&self, entity: &T) -> Result<Option<I>, AkitaError>
         where
@@ -587,10 +1358,11 @@ impl AkitaMapper for Akita {
 
 #[allow(unused)]
 mod test {
-    use std::time::Duration;
+    use std::{sync::{Arc, Barrier}, thread, time::Duration};
     use akita_core::ToValue;
     use once_cell::sync::Lazy;
-    use crate::{Akita, AkitaTable, self as akita, AkitaConfig, LogLevel, AkitaMapper};
+    use crate::{Akita, AkitaError, AkitaTable, self as akita, AkitaConfig, LogLevel, AkitaMapper};
+    use crate::akita::OpenTransactions;
 
     pub static AK:Lazy<Akita> = Lazy::new(|| {
         let mut cfg = AkitaConfig::new("xxxx".to_string());
@@ -611,6 +1383,12 @@ mod test {
         todo!()
     }
 
+    /// the arguments bind in signature order, so a trailing `limit ?` takes the last one
+    #[sql(AK,"select * from mch_info order by mch_no desc limit ?")]
+    fn recent(mut limit: u64) -> Vec<MchInfo> {
+        todo!()
+    }
+
     fn fffff() -> String {
         println!("跑起来啦");
         String::from("test")
@@ -629,4 +1407,32 @@ mod test {
         println!("ssssssss{:?}",data);
         // let s = select("i");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn transactions_per_thread() {
+        let open = Arc::new(OpenTransactions::default());
+        let barrier = Arc::new(Barrier::new(2));
+        let handles = (0..2).map(|_| {
+            let (open, barrier) = (open.clone(), barrier.clone());
+            thread::spawn(move || {
+                let thread = open.enter().unwrap();
+                // both threads hold their transaction at the same time
+                barrier.wait();
+                assert!(matches!(open.enter(), Err(AkitaError::NestedTransaction(_))));
+                barrier.wait();
+                open.leave(thread);
+            })
+        }).collect::<Vec<_>>();
+        handles.into_iter().for_each(|handle| handle.join().unwrap());
+        let thread = open.enter().unwrap();
+        open.leave(thread);
+        assert!(open.enter().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "akita-mysql")]
+    fn test_recent() {
+        let records = recent(10).unwrap();
+        assert!(records.len() <= 10);
+    }
+}
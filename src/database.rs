@@ -1,6 +1,6 @@
-use std::{convert::TryFrom, ops::Deref};
+use std::{convert::TryFrom, io::Write, ops::Deref};
 
-use crate::{cfg_if, Params, TableName, DatabaseName, SchemaContent, TableDef, Rows};
+use crate::{cfg_if, Params, TableName, DatabaseName, SchemaContent, TableDef, Rows, Value};
 use url::Url;
 
 cfg_if! {if #[cfg(feature = "akita-sqlite")]{
@@ -28,6 +28,34 @@ pub trait Database {
 
     fn execute_drop(&mut self, sql: &str, param: Params) -> Result<(), AkitaError>;
 
+    /// Call `f` with the index and the object value of each row, stopping at the first error it returns.
+    /// Returns the number of rows visited. The default buffers the rows through `execute_result`,
+    /// platforms which can read them one by one from the driver override it.
+    fn for_each_row(&mut self, sql: &str, param: Params, f: &mut dyn FnMut(usize, &Value) -> Result<(), AkitaError>) -> Result<usize, AkitaError> {
+        let rows = self.execute_result(sql, param)?;
+        let mut count = 0;
+        for row in rows.iter() {
+            f(count, &row)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Write the single BLOB column of the first row to the writer, returns the written bytes.
+    /// The default reads the row through `execute_result`, platforms which can borrow the driver's buffer override it.
+    fn stream_blob(&mut self, sql: &str, param: Params, writer: &mut dyn Write) -> Result<u64, AkitaError> {
+        let rows = self.execute_result(sql, param)?;
+        if rows.columns.len() != 1 {
+            return Err(AkitaError::DataError(format!("stream_blob expects a single column, got {}", rows.columns.len())));
+        }
+        match rows.data.into_iter().next().and_then(|row| row.into_iter().next()) {
+            Some(Value::Blob(bytes)) => write_blob(writer, &bytes),
+            Some(Value::Text(text)) => write_blob(writer, text.as_bytes()),
+            Some(Value::Nil) | None => Ok(0),
+            Some(v) => Err(AkitaError::DataError(format!("stream_blob expects a BLOB column, got {:?}", v))),
+        }
+    }
+
     fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, AkitaError>;
 
     fn exist_table(&mut self, table_name: &TableName) -> Result<bool, AkitaError>;
@@ -99,6 +127,26 @@ pub trait Database {
     fn flush_privileges(&mut self) -> Result<(), AkitaError>;
 }
 
+/// the object value of a row keyed by the column names
+#[cfg_attr(not(any(feature = "akita-mysql", feature = "akita-sqlite")), allow(dead_code))]
+pub(crate) fn row_object(columns: &[String], record: &[Value]) -> Value {
+    let mut data = Value::new_object();
+    for (column, value) in columns.iter().zip(record.iter()) {
+        data.insert_obj_value(column, value);
+    }
+    data
+}
+
+/// Copy the bytes to the writer in chunks, so a slow writer doesn't hold one huge write.
+pub(crate) fn write_blob(writer: &mut dyn Write, bytes: &[u8]) -> Result<u64, AkitaError> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+    for chunk in bytes.chunks(CHUNK_SIZE) {
+        writer.write_all(chunk).map_err(|e| AkitaError::DataError(format!("Failed to write blob: {}", e)))?;
+    }
+    writer.flush().map_err(|e| AkitaError::DataError(format!("Failed to write blob: {}", e)))?;
+    Ok(bytes.len() as u64)
+}
+
 #[derive(Debug)]
 pub enum DatabasePlatform {
     #[cfg(feature = "akita-mysql")]
@@ -107,6 +155,40 @@ pub enum DatabasePlatform {
     Sqlite(Box<SqliteDatabase>),
 }
 
+impl DatabasePlatform {
+    /// The row limiting fragment appended to a select, e.g. `limit 0, 1`.
+    #[allow(unused_variables)]
+    pub fn limit_clause(&self, offset: usize, count: usize) -> String {
+        match *self {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(_) => format!("limit {}, {}", offset, count),
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => format!("limit {} offset {}", count, offset),
+        }
+    }
+
+    /// The server side id of the connection, used to kill its running statement from another connection.
+    pub fn connection_id(&self) -> Option<u64> {
+        match *self {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(ref mysql) => Some(mysql.connection_id() as u64),
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => None,
+        }
+    }
+
+    /// The most placeholders a single statement may bind.
+    pub fn max_placeholders(&self) -> usize {
+        match *self {
+            #[cfg(feature = "akita-mysql")]
+            DatabasePlatform::Mysql(_) => 65535,
+            // SQLITE_MAX_VARIABLE_NUMBER of sqlite before 3.32
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => 999,
+        }
+    }
+}
+
 impl Deref for DatabasePlatform {
     type Target = dyn Database;
 
@@ -145,7 +227,7 @@ impl<'a> TryFrom<&'a str> for Platform {
     type Error = AkitaError;
 
     fn try_from(s: &'a str) -> Result<Self, Self::Error> {
-        let url = Url::parse(s);
+        let url = parse_url(s);
         match url {
             Ok(url) => {
                 let scheme = url.scheme();
@@ -163,7 +245,91 @@ impl<'a> TryFrom<&'a str> for Platform {
                     _ => Ok(Platform::Unsupported(scheme.to_string())),
                 }
             }
-            Err(e) => Err(AkitaError::UrlParseError(e.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Parse the connection url, the userinfo may contain reserved characters like `@`, `:` or `/`
+/// which are not percent-encoded. The end of the userinfo is the first `@` followed by no other `@`
+/// up to the `?` or `#` starting the query string or fragment, which may contain `@` themselves.
+pub(crate) fn parse_url(s: &str) -> Result<Url, AkitaError> {
+    let (scheme, rest) = match s.find("://") {
+        Some(index) => (&s[..index], &s[index + 3..]),
+        None => return Url::parse(s).map_err(|e| AkitaError::UrlParseError(format!("Invalid url({}): {}", s, e))),
+    };
+    let at = rest.match_indices('@').map(|(index, _)| index).find(|index| {
+        let after = &rest[index + 1..];
+        !after[..after.find(['?', '#']).unwrap_or(after.len())].contains('@')
+    });
+    let (userinfo, authority) = match at {
+        Some(index) => (&rest[..index], &rest[index + 1..]),
+        None => return Url::parse(s).map_err(|e| AkitaError::UrlParseError(format!("Invalid url({}): {}", s, e))),
+    };
+    if !userinfo.contains(['/', '?', '#']) {
+        return Url::parse(s).map_err(|e| AkitaError::UrlParseError(format!("Invalid url({}): {}, the password may contain reserved characters, percent-encode it or use `AkitaConfig::builder()`", s, e)));
+    }
+    let (username, password) = match userinfo.find(':') {
+        Some(index) => (&userinfo[..index], Some(&userinfo[index + 1..])),
+        None => (userinfo, None),
+    };
+    let malformed = |e: String| AkitaError::UrlParseError(format!("Malformed userinfo in url({}://{}:***@{}): {}, percent-encode the password or use `AkitaConfig::builder()`", scheme, username, authority, e));
+    let mut url = Url::parse(&format!("{}://{}", scheme, authority)).map_err(|e| malformed(e.to_string()))?;
+    url.set_username(username).map_err(|_| malformed("can not set the username".to_string()))?;
+    url.set_password(password).map_err(|_| malformed("can not set the password".to_string()))?;
+    Ok(url)
+}
+
+/// Check the number of placeholders (`?` or `$n`) in the sql against the bound parameters,
+/// so a mismatch fails with the statement at hand instead of an opaque driver error.
+/// Named parameters and sql with multiple statements are not checked.
+#[cfg_attr(not(any(feature = "akita-mysql", feature = "akita-sqlite")), allow(dead_code))]
+pub(crate) fn check_param_count(sql: &str, params: &Params) -> Result<(), AkitaError> {
+    let got = match params {
+        Params::Nil => 0,
+        Params::Vector(values) => values.len(),
+        Params::Custom(_) => return Ok(()),
+    };
+    match count_placeholders(sql) {
+        Some(expected) if expected != got => Err(AkitaError::ParamCountMismatch { expected, got, sql: sql.to_string() }),
+        _ => Ok(()),
+    }
+}
+
+/// Count the placeholders outside quotes and comments, `None` for multiple statements.
+fn count_placeholders(sql: &str) -> Option<usize> {
+    let chars = sql.chars().collect::<Vec<char>>();
+    let (mut question, mut numbered) = (0, 0);
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            quote @ ('\'' | '"' | '`') => {
+                i += 1;
+                while i < chars.len() && chars[i] != quote {
+                    if chars[i] == '\\' { i += 1; }
+                    i += 1;
+                }
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' { i += 1; }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') { i += 1; }
+                i += 1;
+            }
+            ';' if chars[i + 1..].iter().any(|c| !c.is_whitespace() && *c != ';') => return None,
+            '?' => question += 1,
+            '$' => {
+                let digits = chars[i + 1..].iter().take_while(|c| c.is_ascii_digit()).collect::<String>();
+                if let Ok(n) = digits.parse::<usize>() {
+                    numbered = numbered.max(n);
+                    i += digits.len();
+                }
+            }
+            _ => {}
         }
+        i += 1;
     }
+    Some(question + numbered)
 }
@@ -0,0 +1,41 @@
+//!
+//! Last query inspection.
+//!
+//! Keeps the last statement executed on the current thread with a summary of its params, to look at in a
+//! debugger or to assert the generated sql in a test without scraping the logs.
+//!
+use std::cell::RefCell;
+
+use crate::Params;
+
+thread_local! {
+    static LAST_QUERY: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Record the statement about to be executed on this thread.
+#[cfg_attr(not(any(feature = "akita-mysql", feature = "akita-sqlite")), allow(dead_code))]
+pub(crate) fn record_last_query(sql: &str, params: &Params) {
+    let query = match params {
+        Params::Nil => sql.to_string(),
+        params => format!("{} -- params: {:?}", sql, params),
+    };
+    LAST_QUERY.with(|last| *last.borrow_mut() = Some(query));
+}
+
+/// The last statement executed on the current thread, `None` before the first one.
+pub fn last_query() -> Option<String> {
+    LAST_QUERY.with(|last| last.borrow().clone())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Params, Value};
+    use crate::debug::{last_query, record_last_query};
+
+    #[test]
+    fn record_per_thread() {
+        record_last_query("select * from t_user where id = ?", &Params::Vector(vec![Value::Int(1)]));
+        assert_eq!(last_query(), Some("select * from t_user where id = ? -- params: Vector([Int(1)])".to_string()));
+        assert_eq!(std::thread::spawn(last_query).join().unwrap(), None);
+    }
+}
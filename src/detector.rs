@@ -0,0 +1,74 @@
+//!
+//! N+1 query detection.
+//!
+//! Counts how often the same query shape (the sql with its literals stripped) runs within a short
+//! window and warns once it passes the threshold, which usually means a query inside a loop that
+//! should be a single `select_by_ids` / `IN` query instead.
+//!
+use std::{collections::HashMap, sync::Mutex, time::{Duration, Instant}};
+
+use once_cell::sync::Lazy;
+
+pub use akita_core::comm::normalize_sql;
+
+static DETECTOR: Lazy<Mutex<QueryDetector>> = Lazy::new(|| Mutex::new(QueryDetector::new(10, Duration::from_secs(1))));
+
+/// Tracks the execution counts of normalized sql within a time window.
+#[derive(Debug)]
+pub struct QueryDetector {
+    threshold: usize,
+    window: Duration,
+    seen: HashMap<String, (Instant, usize)>,
+}
+
+impl QueryDetector {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        QueryDetector { threshold, window, seen: HashMap::new() }
+    }
+
+    /// Record one execution, returns the count when the query shape reaches the threshold within the window.
+    pub fn record(&mut self, sql: &str) -> Option<usize> {
+        let now = Instant::now();
+        let window = self.window;
+        self.seen.retain(|_, (start, _)| now.duration_since(*start) < window);
+        let entry = self.seen.entry(normalize_sql(sql)).or_insert((now, 0));
+        entry.1 += 1;
+        if entry.1 == self.threshold { Some(entry.1) } else { None }
+    }
+}
+
+/// Change the threshold and window of the global detector, the default is 10 executions within one second.
+pub fn set_n_plus_one_threshold(threshold: usize, window: Duration) {
+    if let Ok(mut detector) = DETECTOR.lock() {
+        *detector = QueryDetector::new(threshold, window);
+    }
+}
+
+/// Record one execution on the global detector and warn when it looks like an N+1 pattern.
+#[cfg_attr(not(any(feature = "akita-mysql", feature = "akita-sqlite")), allow(dead_code))]
+pub(crate) fn record_query(sql: &str) {
+    let count = DETECTOR.lock().ok().and_then(|mut detector| detector.record(sql));
+    if let Some(_count) = count {
+        #[cfg(feature = "akita-logging")]
+        log::warn!("[Akita]: possible N+1 query, executed {} times in a short window: {}", _count, normalize_sql(sql));
+        #[cfg(feature = "akita-tracing")]
+        tracing::warn!("[Akita]: possible N+1 query, executed {} times in a short window: {}", _count, normalize_sql(sql));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::detector::{normalize_sql, QueryDetector};
+
+    #[test]
+    fn detect_repeated_shape() {
+        assert_eq!(normalize_sql("select * from t_user where id = 12 and  name = 'akita'"), "select * from t_user where id = ? and name = ?");
+        let mut detector = QueryDetector::new(3, Duration::from_secs(60));
+        assert_eq!(detector.record("select * from t_user where id = 1"), None);
+        assert_eq!(detector.record("select * from t_order where id = 1"), None);
+        assert_eq!(detector.record("select * from t_user where id = 2"), None);
+        assert_eq!(detector.record("select * from t_user where id = 3"), Some(3));
+        assert_eq!(detector.record("select * from t_user where id = 4"), None);
+    }
+}
@@ -1,9 +1,9 @@
 //! 
 //! Common Errors.
 //! 
-use std::{fmt, str::Utf8Error, string::ParseError};
+use std::{fmt, num::{ParseFloatError, ParseIntError}, str::Utf8Error, string::ParseError};
 
-use crate::ConvertError;
+use crate::{AkitaDataError, ConvertError};
 
 
 #[derive(Debug)]
@@ -17,11 +17,18 @@ pub enum AkitaError {
     SQLiteError(String),
     ExcuteSqlError(String, String),
     DataError(String),
+    /// a value which failed to convert, the source text and the parse error
+    Conversion(String, String),
     R2D2Error(String),
     UrlParseError(String),
     RedundantField(String),
     UnknownDatabase(String),
     UnsupportedOperation(String),
+    ParamCountMismatch { expected: usize, got: usize, sql: String },
+    NotUnique(String),
+    QueryCancelled(String),
+    NestedTransaction(String),
+    BatchAborted { committed: u64, error: Box<AkitaError> },
     Unknown,
 }
 
@@ -33,10 +40,16 @@ impl fmt::Display for AkitaError {
             AkitaError::InvalidField(ref err) => err.fmt(f),
             AkitaError::ExcuteSqlError(ref err, ref sql) => write!(f, "SQL Excute Error: {}, SQL: {}", err, sql),
             AkitaError::UnsupportedOperation(ref err) => write!(f, "Unsupported operation: {}", err),
+            AkitaError::ParamCountMismatch { expected, got, ref sql } => write!(f, "Parameter count mismatch: expected {} but got {}, SQL: {}", expected, got, sql),
             AkitaError::UnknownDatabase(ref schema) => write!(f, "Unknown Database URL :{} (Just Support MySQL)", schema),
             AkitaError::MissingIdent(ref err) => err.fmt(f),
+            AkitaError::NotUnique(ref err) => err.fmt(f),
+            AkitaError::QueryCancelled(ref sql) => write!(f, "Query cancelled, SQL: {}", sql),
+            AkitaError::NestedTransaction(ref err) => write!(f, "Nested transaction: {}", err),
+            AkitaError::BatchAborted { committed, ref error } => write!(f, "Batch aborted after {} committed operations: {}", committed, error),
             AkitaError::UrlParseError(ref err) => err.fmt(f),
             AkitaError::DataError(ref err) => err.fmt(f),
+            AkitaError::Conversion(ref value, ref err) => write!(f, "Conversion Error: {}, Value: {}", err, value),
             AkitaError::MissingTable(ref err) => err.fmt(f),
             AkitaError::MissingField(ref err) => err.fmt(f),
             AkitaError::RedundantField(ref err) => err.fmt(f),
@@ -47,6 +60,18 @@ impl fmt::Display for AkitaError {
     }
 }
 
+impl AkitaError {
+    /// Whether the error is a unique key violation, e.g. a duplicate entry of MySQL or a failed UNIQUE constraint of SQLite.
+    pub fn is_unique_violation(&self) -> bool {
+        match *self {
+            AkitaError::ExcuteSqlError(ref err, _) | AkitaError::MySQLError(ref err) | AkitaError::SQLiteError(ref err) => {
+                err.contains("Duplicate entry") || err.contains("UNIQUE constraint failed")
+            }
+            _ => false,
+        }
+    }
+}
+
 #[allow(deprecated, deprecated_in_future)]
 impl std::error::Error for AkitaError {
     fn description(&self) -> &str {
@@ -57,9 +82,15 @@ impl std::error::Error for AkitaError {
             AkitaError::ExcuteSqlError(ref err, ref _sql) => err,
             AkitaError::InvalidField(ref err) => err,
             AkitaError::UnsupportedOperation(ref err) => err,
+            AkitaError::ParamCountMismatch { .. } => "Parameter count mismatch",
             AkitaError::UrlParseError(ref err) => err,
             AkitaError::MissingIdent(ref err) => err,
+            AkitaError::NotUnique(ref err) => err,
+            AkitaError::QueryCancelled(ref _sql) => "Query cancelled",
+            AkitaError::NestedTransaction(ref err) => err,
+            AkitaError::BatchAborted { .. } => "Batch aborted",
             AkitaError::DataError(ref err) => err,
+            AkitaError::Conversion(ref _value, ref err) => err,
             AkitaError::MissingTable(ref err) => err,
             AkitaError::MissingField(ref err) => err,
             AkitaError::RedundantField(ref err) => err,
@@ -83,12 +114,47 @@ impl From<ParseError> for AkitaError {
         AkitaError::UrlParseError(err.to_string())
     }
 }
+
+impl From<url::ParseError> for AkitaError {
+    fn from(err: url::ParseError) -> Self {
+        AkitaError::UrlParseError(err.to_string())
+    }
+}
+
+impl From<ParseIntError> for AkitaError {
+    fn from(err: ParseIntError) -> Self {
+        ConvertError::from(err).into()
+    }
+}
+
+impl From<ParseFloatError> for AkitaError {
+    fn from(err: ParseFloatError) -> Self {
+        ConvertError::from(err).into()
+    }
+}
+
+impl From<chrono::ParseError> for AkitaError {
+    fn from(err: chrono::ParseError) -> Self {
+        ConvertError::from(err).into()
+    }
+}
+
+impl From<AkitaDataError> for AkitaError {
+    fn from(err: AkitaDataError) -> Self {
+        match err {
+            AkitaDataError::ConvertError(err) => err.into(),
+            AkitaDataError::NoSuchValueError(err) | AkitaDataError::ObjectValidError(err) => AkitaError::DataError(err),
+        }
+    }
+}
+
 impl From<ConvertError> for AkitaError {
     fn from(err: ConvertError) -> Self {
         match err {
             ConvertError::NotSupported(v, ty) => {
                 AkitaError::DataError(format!("[{}]:{}", ty, v))
             }
+            ConvertError::Parse(v, err) => AkitaError::Conversion(v, err),
         }
     }
 }
@@ -132,4 +198,18 @@ impl From<mysql::FromRowError> for AkitaError {
     fn from(err: mysql::FromRowError) -> Self {
         AkitaError::MySQLError(err.to_string())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{AkitaError, FromValue, Value};
+
+    #[test]
+    fn conversion_error() {
+        match i64::from_value_opt(&Value::Text("4x2".to_string())).map_err(AkitaError::from) {
+            Err(AkitaError::Conversion(value, _)) => assert_eq!(value, "4x2"),
+            other => panic!("expected a conversion error, got {:?}", other),
+        }
+        assert!(matches!("x".parse::<i32>().map_err(AkitaError::from), Err(AkitaError::Conversion(_, _))));
+    }
+}
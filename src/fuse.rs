@@ -2,7 +2,7 @@
 //! Fuse Features
 //!
 
-use akita_core::{FromValue, GetFields, Rows, ToValue, Value};
+use akita_core::{FromValue, GetFields, Params, Rows, ToValue, Value};
 use crate::{Akita, AkitaError, AkitaMapper, IPage, ISegment, Wrapper};
 use crate::database::DatabasePlatform;
 
@@ -10,11 +10,13 @@ pub struct Fuse<'a> {
     akita: &'a Akita,
     wrapper: Wrapper,
     table: String,
+    affected_rows: u64,
+    last_insert_id: u64,
 }
 
 impl<'a> Fuse<'a> {
     pub fn new(akita: &'a Akita) -> Self {
-        Self { akita, wrapper: Wrapper::new(), table: String::default() }
+        Self { akita, wrapper: Wrapper::new(), table: String::default(), affected_rows: 0, last_insert_id: 0 }
     }
 
     pub fn wrapper(mut self, wrapper: Wrapper) -> Self {
@@ -27,12 +29,24 @@ impl<'a> Fuse<'a> {
         self
     }
 
+    /// The rows changed by the last `remove`, `update` or `save_map*` of this fuse.
     pub fn affected_rows(&self) -> u64 {
-        self.akita.affected_rows()
+        self.affected_rows
     }
 
+    /// The id generated by the last `save_map*` of this fuse.
     pub fn last_insert_id(&self) -> u64 {
-        self.akita.last_insert_id()
+        self.last_insert_id
+    }
+
+    /// Run the statement and read the affected rows and the generated id on its own connection,
+    /// any other pooled connection would report the state of its own session.
+    fn execute_mutation<P: Into<Params>>(&mut self, sql: &str, params: P) -> Result<(), AkitaError> {
+        let mut conn = self.akita.acquire()?;
+        conn.execute_result(sql, params.into())?;
+        self.affected_rows = conn.affected_rows();
+        self.last_insert_id = conn.last_insert_id();
+        Ok(())
     }
 
     pub fn list<T>(&mut self) -> Result<Vec<T>, AkitaError>
@@ -140,8 +154,7 @@ impl<'a> Fuse<'a> {
         let where_condition = wrapper.get_sql_segment();
         let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
         let sql = format!("delete from {} {}", &self.table, where_condition);
-        let _ = self.akita.exec_iter(&sql, ())?;
-        Ok(())
+        self.execute_mutation(&sql, ())
     }
 
     /// Update the records by wrapper.
@@ -152,11 +165,10 @@ impl<'a> Fuse<'a> {
         let sql = self.build_update_clause()?;
         let update_fields = &self.wrapper.fields_set;
         if update_fields.is_empty() {
-            return Err(AkitaError::MissingField("Update Error, Missing update fields !".to_string()))
+            Err(AkitaError::MissingField("Update Error, Missing update fields !".to_string()))
         } else {
-            self.akita.exec_iter(&sql, ())?;
+            self.execute_mutation(&sql, ())
         }
-        Ok(())
     }
 
     /// called multiple times when using database platform that doesn;t support multiple value
@@ -175,15 +187,12 @@ impl<'a> Fuse<'a> {
         if entities.len() == 0 {
             return Err(AkitaError::DataError("data cannot be empty".to_string()))
         }
-        let columns = entities[0].to_value();
-        let columns = if let Some(columns) = columns.as_object() {
-            columns.keys().collect::<Vec<&String>>()
-        } else { Vec::new() };
+        let columns = map_batch_columns(entities);
         let sql = self.build_insert_clause_map(entities)?;
-        let mut values: Vec<Value> = Vec::with_capacity(columns.len());
+        let mut values: Vec<Value> = Vec::with_capacity(entities.len() * columns.len());
         for entity in entities.iter() {
+            let data = entity.to_value();
             for col in columns.iter() {
-                let data = entity.to_value();
                 let value = data.get_obj_value(col);
                 match value {
                     Some(value) => values.push(value.clone()),
@@ -191,9 +200,7 @@ impl<'a> Fuse<'a> {
                 }
             }
         }
-        let _bvalues: Vec<&Value> = values.iter().collect();
-        self.akita.exec_iter(&sql,values)?;
-        Ok(())
+        self.execute_mutation(&sql, values)
     }
     /// build an update clause
     fn build_update_clause(&mut self) -> Result<String, AkitaError> {
@@ -210,7 +217,7 @@ impl<'a> Fuse<'a> {
                 .enumerate()
                 .map(|(x, (col, value))| {
                     #[allow(unreachable_patterns)]
-                    match db {
+                    match *db {
                         #[cfg(feature = "akita-mysql")]
                         DatabasePlatform::Mysql(_) => format!("`{}` = {}", col, value.get_sql_segment()),
                         #[cfg(feature = "akita-sqlite")]
@@ -290,10 +297,7 @@ impl<'a> Fuse<'a> {
         if entities.len() == 0 {
             return Err(AkitaError::DataError("data cannot be empty".to_string()))
         }
-        let columns = entities[0].to_value();
-        let columns = if let Some(columns) = columns.as_object() {
-            columns.keys().collect::<Vec<&String>>()
-        } else { Vec::new() };
+        let columns = map_batch_columns(entities);
         let columns_len = columns.len();
         let mut sql = String::new();
         let db = self.akita.acquire()?;
@@ -318,7 +322,7 @@ impl<'a> Fuse<'a> {
                         .enumerate()
                         .map(|(x, _)| {
                             #[allow(unreachable_patterns)]
-                            match db {
+                            match *db {
                                 #[cfg(feature = "with-sqlite")]
                                 DatabasePlatform::Sqlite(_) => format!("${}", y * columns_len + x + 1),
                                 #[cfg(feature = "akita-mysql")]
@@ -366,7 +370,7 @@ impl<'a> Fuse<'a> {
                         .enumerate()
                         .map(|(x, _)| {
                             #[allow(unreachable_patterns)]
-                            match db {
+                            match *db {
                                 #[cfg(feature = "with-sqlite")]
                                 DatabasePlatform::Sqlite(_) => format!("${}", y * columns_len + x + 1),
                                 #[cfg(feature = "akita-mysql")]
@@ -382,4 +386,51 @@ impl<'a> Fuse<'a> {
             .join(", ");
         Ok(sql)
     }
-}
\ No newline at end of file
+}
+/// collect the insert columns of a map batch, every key seen in any entity in first-seen order,
+/// so rows that leave different fields unset still bind the same column list
+fn map_batch_columns<T>(entities: &[T]) -> Vec<String>
+    where
+        T: ToValue,
+{
+    let mut columns: Vec<String> = Vec::new();
+    for entity in entities.iter() {
+        if let Some(data) = entity.to_value().as_object() {
+            for key in data.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.to_owned());
+                }
+            }
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use crate::fuse::map_batch_columns;
+
+    #[test]
+    fn map_batch_columns_heterogeneous() {
+        let first = json!({ "id": 1 });
+        let second = json!({ "id": 2, "name": "akita" });
+        let third = json!({ "age": 3, "id": 3 });
+        assert_eq!(map_batch_columns(&[&first, &second, &third]), vec!["id".to_string(), "name".to_string(), "age".to_string()]);
+    }
+
+    #[cfg(feature = "akita-sqlite")]
+    #[test]
+    fn last_insert_id_of_own_connection() {
+        use crate::{Akita, AkitaConfig, AkitaMapper, ToValue};
+        #[derive(ToValue)]
+        struct Named { name: String }
+        let cfg = AkitaConfig::new("sqlite://./../../example/akita.sqlite3".to_string()).set_max_size(2);
+        let akita = Akita::new(cfg).unwrap();
+        akita.exec_drop("CREATE TABLE IF NOT EXISTS t_fuse_seq (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)", ()).unwrap();
+        let mut fuse = akita.fuse().table("t_fuse_seq");
+        fuse.save_map(&Named { name: "akita".to_string() }).unwrap();
+        let id: i64 = akita.exec_first("SELECT max(id) FROM t_fuse_seq", ()).unwrap();
+        assert_eq!((fuse.affected_rows(), fuse.last_insert_id()), (1, id as u64));
+    }
+}
@@ -52,7 +52,7 @@
 //! 
 //! ## Example
 //! 
-//! ```rust
+//! ```rust,no_run
 //! # use akita::*;
 //! # use chrono::{NaiveDateTime, NaiveDate};
 //! # use std::time::Duration;
@@ -73,7 +73,7 @@
 //!     pub birthday: Option<NaiveDate>,
 //!     /// 性别
 //!     pub gender: u8,
-//!     #[field(exist = "false")]
+//!     #[field(exist = false)]
 //!     pub is_org: bool,
 //!     #[field(name = "token")]
 //!     pub url_token: String,
@@ -86,15 +86,16 @@
 //!         .set_connection_timeout(Duration::from_secs(6))
 //!         .set_log_level(LogLevel::Info).set_max_size(6);
 //!     let akita = Akita::new(cfg).expect("must be ok");
+//!     let name = "%uu%";
 //!     // The Wrapper to build query condition
 //!     let wrapper = Wrapper::new()
 //!         .eq("username", "ussd") // username = 'ussd'
-//!         .gt("age", 1) //! age > 1
+//!         .gt("age", 1) // age > 1
 //!         .lt("age", 10) // age < 10
 //!         .inside("user_type", vec!["admin", "super"]) // user_type in ('admin', 'super')
 //!         .and(|wrapper| { // or
-//!             wrapper.like("username", &name)
-//!                 .or_direct().like("username", &name)
+//!             wrapper.like("username", name)
+//!                 .or_direct().like("username", name)
 //!         });
 //!     // CRUD with Akita
 //!     let insert_id: Option<i32> = akita.save(&User::default()).unwrap();
@@ -154,8 +155,8 @@
 //!     fn select_example(id: &str) -> Vec<User> { todo!() }
 //!
 //!     // or:
-//!     #[sql(AK,"select * from user where mch_no = ?")]
-//!     fn select_example2(ak: &AKita, id: &str) -> Vec<User> { todo!() }
+//!     #[sql(ak,"select * from user where mch_no = ?")]
+//!     fn select_example2(ak: &Akita, id: &str) -> Vec<User> { todo!() }
 //! }
 //! ```
 //! ## API Documentation
@@ -188,13 +189,18 @@ mod manager;
 #[allow(unused)]
 #[cfg(feature = "akita-fuse")]
 mod fuse;
+#[cfg(feature = "akita-detect")]
+mod detector;
+#[cfg(feature = "akita-debug")]
+mod debug;
+mod metrics;
 mod akita;
 
 
 #[doc(inline)]
-pub use wrapper::Wrapper;
+pub use wrapper::{Wrapper, JoinKind};
 #[doc(inline)]
-pub use database::Platform;
+pub use database::{Database, DatabasePlatform, Platform};
 #[doc(inline)]
 pub use mapper::{BaseMapper, IPage, AkitaMapper};
 #[doc(inline)]
@@ -202,11 +208,17 @@ pub use segment::{Segment, AkitaKeyword, ISegment};
 #[doc(inline)]
 pub use errors::AkitaError;
 #[doc(inline)]
-pub use pool::{AkitaConfig, LogLevel, Pool};
+pub use pool::{AkitaConfig, AkitaConfigBuilder, LogLevel, Pool};
 #[cfg(feature = "akita-auth")]
 pub use auth::*;
 #[cfg(feature = "akita-fuse")]
 pub use fuse::*;
+#[cfg(feature = "akita-detect")]
+pub use detector::{QueryDetector, normalize_sql, set_n_plus_one_threshold};
+#[cfg(feature = "akita-debug")]
+pub use debug::last_query;
+#[doc(inline)]
+pub use metrics::{AkitaMetrics, sql_hash};
 pub use akita::*;
 #[doc(inline)]
 pub use manager::{AkitaEntityManager};
@@ -1,3 +1,4 @@
+use std::{collections::HashMap, hash::Hash, sync::Arc};
 use crate::{AkitaError, IPage, Wrapper, database::{DatabasePlatform}, mapper::AkitaMapper, GetFields, GetTableName, FromValue, ToValue, Rows, TableName, DatabaseName, FieldName, Params, Value, FieldType, TableDef, segment::ISegment, AkitaConfig, Akita};
 use crate::pool::PlatformPool;
 
@@ -9,6 +10,8 @@ pub struct AkitaEntityManager(PlatformPool, AkitaConfig);
 
 pub struct AkitaTransaction<'a> {
     pub(crate) conn: &'a Akita,
+    /// the thread that started the transaction
+    pub(crate) thread: std::thread::ThreadId,
     pub committed: bool,
     pub rolled_back: bool,
 }
@@ -35,6 +38,7 @@ impl AkitaTransaction <'_> {
 impl<'a> Drop for AkitaTransaction<'a> {
     /// Will rollback transaction.
     fn drop(&mut self) {
+        self.conn.transactions.leave(self.thread);
         if !self.committed && !self.rolled_back {
             // match self.conn.acquire() {
             //     Ok(mut conn) => {
@@ -87,6 +91,31 @@ impl AkitaMapper for AkitaTransaction <'_> {
         self.conn.page(page, size, wrapper)
     }
 
+    /// Get table of records with page, without running the count query.
+    fn page_records<T>(&self, page: usize, size: usize, wrapper: Wrapper) -> Result<Vec<T>, AkitaError>
+    where
+        T: GetTableName + GetFields + FromValue,
+    {
+        self.conn.page_records(page, size, wrapper)
+    }
+
+    /// Get all the table of records as a shared slice.
+    fn list_arc<T>(&self, wrapper: Wrapper) -> Result<Arc<[T]>, AkitaError>
+    where
+        T: GetTableName + GetFields + FromValue,
+    {
+        self.conn.list_arc(wrapper)
+    }
+
+    /// Count the records per value of `group_col`.
+    fn count_group_by<T, K>(&self, group_col: &str, wrapper: Wrapper) -> Result<HashMap<K, usize>, AkitaError>
+    where
+        T: GetTableName + GetFields,
+        K: FromValue + Eq + Hash,
+    {
+        self.conn.count_group_by::<T, K>(group_col, wrapper)
+    }
+
     /// Get the total count of records
     fn count<T>(&self, wrapper:Wrapper) -> Result<usize, AkitaError>
     where
@@ -132,7 +161,7 @@ impl AkitaMapper for AkitaTransaction <'_> {
     }
 
     #[allow(unused_variables)]
-    fn save_batch<T>(&self, entities: &[&T]) -> Result<(), AkitaError>
+    fn save_batch<T>(&self, entities: &[&T]) -> Result<u64, AkitaError>
     where
         T: GetTableName + GetFields + ToValue
     {
@@ -148,6 +177,18 @@ impl AkitaMapper for AkitaTransaction <'_> {
         self.conn.save(entity)
     }
 
+    fn save_returning_column<T, I>(&self, entity: &T, id_col: &str) -> Result<I, AkitaError> where T: GetTableName + GetFields + ToValue, I: FromValue {
+        self.conn.save_returning_column(entity, id_col)
+    }
+
+    fn save_or_update_batch_on_conflict<T>(&self, entities: &[&T], update_cols: &[&str]) -> Result<u64, AkitaError> where T: GetTableName + GetFields + ToValue {
+        self.conn.save_or_update_batch_on_conflict(entities, update_cols)
+    }
+
+    fn save_replace<T>(&self, entity: &T) -> Result<u64, AkitaError> where T: GetTableName + GetFields + ToValue {
+        self.conn.save_replace(entity)
+    }
+
     fn save_or_update<T, I>(&self, entity: &T) -> Result<Option<I>, AkitaError> where T: GetTableName + GetFields + ToValue, I: FromValue {
         self.conn.save_or_update(entity)
     }
@@ -206,39 +247,16 @@ impl AkitaEntityManager{
         conn.get_database_name()
     }
 
-    fn save_batch_inner<T>(&self, entities: &[&T]) -> Result<(), AkitaError>
+    fn save_batch_inner<T>(&self, entities: &[&T]) -> Result<u64, AkitaError>
     where
         T: GetTableName + GetFields + ToValue
     {
         let mut conn = self.acquire()?;
-        let columns = T::fields();
         let sql = build_insert_clause(&conn, entities);
 
-        let mut values: Vec<Value> = Vec::with_capacity(entities.len() * columns.len());
-        for entity in entities.iter() {
-            for col in columns.iter() {
-                let data = entity.to_value();
-                let mut value = data.get_obj_value(&col.name);
-                match &col.fill {
-                    None => {}
-                    Some(v) => {
-                        match v.mode.as_ref() {
-                            "insert" | "default" => {
-                                value = v.value.as_ref();
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                match value {
-                    Some(value) => values.push(value.clone()),
-                    None => values.push(Value::Nil),
-                }
-            }
-        }
-        let bvalues: Vec<&Value> = values.iter().collect();
+        let values = build_insert_values(entities);
         conn.execute_result(&sql,values.into())?;
-        Ok(())
+        Ok(conn.affected_rows())
     }
 
     /// build the returning clause
@@ -262,13 +280,13 @@ pub fn build_insert_clause<T>(platform: &DatabasePlatform, entities: &[&T]) -> S
 {
     let table = T::table_name();
     let columns = T::fields();
-    let columns_len = columns.len();
+    let columns_len = columns.iter().filter(|f| f.insertable()).count();
     let mut sql = String::new();
     sql += &format!("INSERT INTO {} ", table.complete_name());
     sql += &format!(
         "({})\n",
         columns
-            .iter().filter(|f| f.exist)
+            .iter().filter(|f| f.insertable())
             .map(|c| format!("`{}`", c.name))
             .collect::<Vec<_>>()
             .join(", ")
@@ -281,7 +299,7 @@ pub fn build_insert_clause<T>(platform: &DatabasePlatform, entities: &[&T]) -> S
             format!(
                 "\n\t({})",
                 columns
-                    .iter().filter(|f| f.exist)
+                    .iter().filter(|f| f.insertable())
                     .enumerate()
                     .map(|(x, _)| {
                         #[allow(unreachable_patterns)]
@@ -302,6 +320,365 @@ pub fn build_insert_clause<T>(platform: &DatabasePlatform, entities: &[&T]) -> S
     sql
 }
 
+/// build the values to bind for an insert clause
+pub fn build_insert_values<T>(entities: &[&T]) -> Vec<Value>
+    where
+        T: GetTableName + GetFields + ToValue,
+{
+    let columns = T::fields();
+    let mut values: Vec<Value> = Vec::with_capacity(entities.len() * columns.len());
+    for entity in entities.iter() {
+        let data = entity.to_value();
+        for col in columns.iter().filter(|f| f.insertable()) {
+            let mut value = data.get_obj_value(&col.name);
+            match &col.fill {
+                None => {}
+                Some(v) => {
+                    match v.mode.as_ref() {
+                        "insert" | "default" => {
+                            value = v.value.as_ref();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            match value {
+                Some(value) => values.push(value.clone()),
+                None => values.push(Value::Nil),
+            }
+        }
+    }
+    values
+}
+
+/// insert the entities in chunks that fit the placeholder limit of the platform,
+/// updating `update_cols` of the records whose key already exists, returns the total affected rows
+#[allow(unused)]
+pub fn save_batch_on_conflict<T>(conn: &mut DatabasePlatform, entities: &[&T], update_cols: &[&str]) -> Result<u64, AkitaError>
+    where
+        T: GetTableName + GetFields + ToValue,
+{
+    let table = T::table_name();
+    if table.complete_name().is_empty() {
+        return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+    }
+    let columns = T::fields();
+    if let Some(col) = update_cols.iter().find(|col| !columns.iter().any(|f| f.exist && f.name.eq(*col))) {
+        return Err(AkitaError::InvalidField(format!("Table({}) Unknown Column: {}", &table.name, col)));
+    }
+    if entities.is_empty() || update_cols.is_empty() {
+        return Err(AkitaError::DataError("entities and update columns cannot be empty".to_string()))
+    }
+    let id = columns.iter().find(|field| match field.field_type {
+        FieldType::TableId(_) => true,
+        FieldType::TableField => false,
+    }).ok_or_else(|| AkitaError::MissingIdent(format!("Table({}) Missing Ident...", &table.name)))?;
+    let on_conflict: String = match conn {
+        #[cfg(feature = "akita-mysql")]
+        DatabasePlatform::Mysql(_) => format!("ON DUPLICATE KEY UPDATE {}", update_cols.iter().map(|col| format!("`{}` = VALUES(`{}`)", col, col)).collect::<Vec<_>>().join(", ")),
+        #[cfg(feature = "akita-sqlite")]
+        DatabasePlatform::Sqlite(_) => format!("ON CONFLICT(`{}`) DO UPDATE SET {}", id.name, update_cols.iter().map(|col| format!("`{}` = excluded.`{}`", col, col)).collect::<Vec<_>>().join(", ")),
+        _ => return Err(AkitaError::UnsupportedOperation("The platform does not support upsert".to_string())),
+    };
+    let columns_len = columns.iter().filter(|f| f.insertable()).count().max(1);
+    let chunk_size = (conn.max_placeholders() / columns_len).max(1);
+    let mut affected_rows = 0;
+    for chunk in entities.chunks(chunk_size) {
+        let sql = format!("{} {}", build_insert_clause(conn, chunk), on_conflict);
+        conn.execute_result(&sql, build_insert_values(chunk).into())?;
+        affected_rows += conn.affected_rows();
+    }
+    Ok(affected_rows)
+}
+
+/// `delete ... in (...)` with one placeholder per id, `$1, $2, ...` when `numbered`.
+pub(crate) fn build_delete_by_ids_clause(table: &str, id_col: &str, count: usize, numbered: bool) -> String {
+    let placeholders = (1..=count).map(|i| if numbered { format!("${}", i) } else { "?".to_string() }).collect::<Vec<_>>();
+    format!("delete from {} where `{}` in ({})", table, id_col, placeholders.join(", "))
+}
+
+/// Delete the records of the ids on the connection, each id bound on its own.
+pub(crate) fn remove_by_ids_on<T, I>(conn: &mut DatabasePlatform, ids: Vec<I>) -> Result<u64, AkitaError>
+    where
+        I: ToValue,
+        T: GetTableName + GetFields,
+{
+    let table = T::table_name();
+    let field = T::fields().into_iter().find(|field| match field.field_type {
+        FieldType::TableId(_) => true,
+        FieldType::TableField => false,
+    }).ok_or_else(|| AkitaError::MissingIdent(format!("Table({}) Missing Ident...", &table.name)))?;
+    #[allow(unreachable_patterns)]
+    let numbered = match conn {
+        #[cfg(feature = "akita-mysql")]
+        DatabasePlatform::Mysql(_) => false,
+        _ => true,
+    };
+    let sql = build_delete_by_ids_clause(&table.complete_name(), &field.name, ids.len(), numbered);
+    let values = ids.iter().map(|id| id.to_value()).collect::<Vec<Value>>();
+    conn.execute_result(&sql, values.into())?;
+    Ok(conn.affected_rows())
+}
+
+/// The select of `list` and `list_arc`, the wrapper with its values bound and its limit: the sql and its params.
+pub(crate) fn list_query<T>(conn: &DatabasePlatform, wrapper: Wrapper) -> (String, Params)
+    where
+        T: GetTableName + GetFields,
+{
+    let table = T::table_name();
+    let (mut wrapper, params) = wrapper.bind_params(conn);
+    let select_fields = wrapper.get_select_sql();
+    let enumerated_columns = if select_fields.eq("*") {
+        T::select_columns().to_string()
+    } else {
+        select_fields
+    };
+    let (from, where_condition) = wrapper.get_from_sql(&table.complete_name());
+    (format!("SELECT {} FROM {} {} {}", &enumerated_columns, from, where_condition, wrapper.limit_sql(conn)), params)
+}
+
+/// The records of the wrapper on the connection mapped straight from the row buffer into a shared slice, the select of `list`.
+pub(crate) fn list_arc_on<T>(conn: &mut DatabasePlatform, wrapper: Wrapper) -> Result<Arc<[T]>, AkitaError>
+    where
+        T: GetTableName + GetFields + FromValue,
+{
+    let (sql, params) = list_query::<T>(conn, wrapper);
+    let rows = conn.execute_result(&sql, params)?;
+    Ok(rows_into_arc(&rows))
+}
+
+/// Map the rows into a shared slice, the mapping iterator knows its exact length so the slice is allocated
+/// once up front, rather than collected into a `Vec` and copied over like `Arc::from(list(..))`.
+pub(crate) fn rows_into_arc<T>(rows: &Rows) -> Arc<[T]>
+    where
+        T: FromValue,
+{
+    rows.data.iter().map(|row| {
+        let mut data = Value::new_object();
+        for (column, value) in rows.columns.iter().zip(row.iter()) {
+            data.insert_obj_value(column, value);
+        }
+        T::from_value(&data)
+    }).collect()
+}
+
+/// The count of the records per value of the column on the connection, the column is checked by the caller.
+pub(crate) fn count_group_by_on<T, K>(conn: &mut DatabasePlatform, group_col: &str, wrapper: Wrapper) -> Result<HashMap<K, usize>, AkitaError>
+    where
+        T: GetTableName + GetFields,
+        K: FromValue + Eq + Hash,
+{
+    let table = T::table_name();
+    let group_col = format!("`{}`", group_col);
+    let (mut wrapper, params) = wrapper.group_by(vec![group_col.to_owned()]).bind_params(conn);
+    let (from, where_condition) = wrapper.get_from_sql(&table.complete_name());
+    let rows = conn.execute_result(&format!("SELECT {}, COUNT(1) FROM {} {}", &group_col, from, where_condition), params)?;
+    rows.data.iter().map(|row| {
+        let key = K::from_value_opt(row.first().unwrap_or(&Value::Nil))?;
+        let count = usize::from_value_opt(row.get(1).unwrap_or(&Value::Nil))?;
+        Ok((key, count))
+    }).collect()
+}
+
+/// The queries of a page shared by `page` and `page_records`, the wrapper ordered by `default_page_order` with its values bound:
+/// the count sql, the select sql to be followed by the limit of the page, and the params of both.
+pub(crate) fn page_query<T>(conn: &DatabasePlatform, cfg: &AkitaConfig, wrapper: Wrapper) -> (String, String, Params)
+    where
+        T: GetTableName + GetFields,
+{
+    let table = T::table_name();
+    let wrapper = default_page_order::<T>(cfg, wrapper);
+    let (mut wrapper, params) = wrapper.bind_params(conn);
+    let select_fields = wrapper.get_select_sql();
+    let enumerated_columns = if select_fields.eq("*") {
+        T::select_columns().to_string()
+    } else {
+        select_fields
+    };
+    let (from, where_condition) = wrapper.get_from_sql(&table.complete_name());
+    let count_sql = wrapper.get_count_sql(&from, &where_condition);
+    (count_sql, format!("SELECT {} FROM {} {}", &enumerated_columns, from, where_condition), params)
+}
+
+/// The records of the page on the connection, the select of `page` without its count.
+pub(crate) fn page_records_on<T>(conn: &mut DatabasePlatform, cfg: &AkitaConfig, page: usize, size: usize, wrapper: Wrapper) -> Result<Vec<T>, AkitaError>
+    where
+        T: GetTableName + GetFields + FromValue,
+{
+    let (_, sql, params) = page_query::<T>(conn, cfg, wrapper);
+    let offset = IPage::<T>::new(page, size, 0, vec![]).offset();
+    let sql = format!("{} {}", sql, conn.limit_clause(offset, size));
+    let rows = conn.execute_result(&sql, params)?;
+    Ok(rows.iter().map(|data| T::from_value(&data)).collect::<Vec<T>>())
+}
+
+/// A multi-row `INSERT` of the columns with one placeholder per value, `$1, $2, ...` when `numbered`.
+pub(crate) fn build_bulk_insert_clause(table: &str, columns: &[&str], rows: usize, numbered: bool) -> String {
+    let row = |y: usize| (1..=columns.len()).map(|x| if numbered { format!("${}", y * columns.len() + x) } else { "?".to_string() }).collect::<Vec<_>>().join(", ");
+    let values = (0..rows).map(|y| format!("({})", row(y))).collect::<Vec<_>>().join(", ");
+    format!("INSERT INTO {} ({}) VALUES {}", table, columns.iter().map(|col| format!("`{}`", col)).collect::<Vec<_>>().join(", "), values)
+}
+
+/// Append a row in the default format of `LOAD DATA`, tab separated fields ended by a newline, `\N` for NULL,
+/// the backslash, tab, newline, carriage return and NUL escaped with a backslash.
+#[cfg_attr(not(feature = "akita-mysql"), allow(dead_code))]
+pub(crate) fn infile_row(row: &[Value], buf: &mut Vec<u8>) {
+    for (i, value) in row.iter().enumerate() {
+        if i > 0 {
+            buf.push(b'\t');
+        }
+        let text = match value {
+            Value::Nil => { buf.extend_from_slice(b"\\N"); continue; }
+            Value::Bool(v) => if *v { "1".to_string() } else { "0".to_string() },
+            Value::Blob(v) => { escape_infile(v, buf); continue; }
+            Value::Timestamp(v) => v.naive_utc().format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+            Value::DateTime(v) => v.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+            other => other.to_string(),
+        };
+        escape_infile(text.as_bytes(), buf);
+    }
+    buf.push(b'\n');
+}
+
+#[cfg_attr(not(feature = "akita-mysql"), allow(dead_code))]
+fn escape_infile(bytes: &[u8], buf: &mut Vec<u8>) {
+    for byte in bytes {
+        match byte {
+            b'\\' => buf.extend_from_slice(b"\\\\"),
+            b'\t' => buf.extend_from_slice(b"\\t"),
+            b'\n' => buf.extend_from_slice(b"\\n"),
+            b'\r' => buf.extend_from_slice(b"\\r"),
+            0 => buf.extend_from_slice(b"\\0"),
+            _ => buf.push(*byte),
+        }
+    }
+}
+
+/// The largest value of an integer column type such as `INT UNSIGNED`, `None` for the other types.
+pub(crate) fn id_type_max(sql_type: &str) -> Option<u64> {
+    let sql_type = sql_type.to_uppercase();
+    let unsigned = sql_type.contains("UNSIGNED");
+    let bits = match sql_type.split(|c: char| c == '(' || c.is_whitespace()).next().unwrap_or_default() {
+        "TINYINT" => 8,
+        "SMALLINT" => 16,
+        "MEDIUMINT" => 24,
+        "INT" | "INTEGER" => 32,
+        "BIGINT" => 64,
+        _ => return None,
+    };
+    Some(if unsigned { u64::MAX >> (64 - bits) } else { u64::MAX >> (65 - bits) })
+}
+
+/// Order the wrapper of a page by the primary key when it has no ordering, see `AkitaConfig::set_page_default_order`.
+pub(crate) fn default_page_order<T: GetTableName + GetFields>(cfg: &AkitaConfig, wrapper: Wrapper) -> Wrapper {
+    if !wrapper.expression.order_by.segments.is_empty() {
+        return wrapper;
+    }
+    let id_field = T::fields().into_iter().find(|f| matches!(f.field_type, FieldType::TableId(_)));
+    match id_field {
+        Some(field) if cfg.page_default_order() => wrapper.asc_by(vec![field.name]),
+        _ => {
+            #[cfg(feature = "akita-logging")]
+            log::warn!("[Akita]: page of {} without order by, the rows of a page are not deterministic", T::table_name().complete_name());
+            #[cfg(feature = "akita-tracing")]
+            tracing::warn!("[Akita]: page of {} without order by, the rows of a page are not deterministic", T::table_name().complete_name());
+            wrapper
+        }
+    }
+}
+
+/// Warn when the last generated id passes the configured fraction of the id column's maximum.
+pub(crate) fn warn_id_overflow<T: GetTableName + GetFields>(cfg: &AkitaConfig, last_insert_id: u64) {
+    let ratio = match cfg.id_overflow_warning() {
+        Some(ratio) => ratio,
+        None => return,
+    };
+    let id_field = T::fields().into_iter().find(|f| matches!(f.field_type, FieldType::TableId(_)));
+    let max = id_field.and_then(|f| id_type_max(f.sql_type.as_deref().unwrap_or("BIGINT"))).unwrap_or(i64::MAX as u64);
+    if last_insert_id as f64 >= max as f64 * ratio {
+        #[cfg(feature = "akita-logging")]
+        log::warn!("[Akita]: the id of {} reached {}, {:.1}% of its maximum {}", T::table_name().complete_name(), last_insert_id, last_insert_id as f64 * 100.0 / max as f64, max);
+        #[cfg(feature = "akita-tracing")]
+        tracing::warn!("[Akita]: the id of {} reached {}, {:.1}% of its maximum {}", T::table_name().complete_name(), last_insert_id, last_insert_id as f64 * 100.0 / max as f64, max);
+    }
+}
+
+/// Quote a plain or schema qualified table name part by part, e.g. `` `db`.`t_user` ``,
+/// anything but letters, digits and `_` in a part is an error.
+pub(crate) fn quote_table_name(table: &TableName) -> Result<String, AkitaError> {
+    let parts = table.schema.iter().chain(std::iter::once(&table.name)).collect::<Vec<_>>();
+    if parts.iter().any(|part| part.is_empty() || !part.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+        return Err(AkitaError::MissingTable(format!("Invalid table name: {}", table.complete_name())));
+    }
+    Ok(parts.iter().map(|part| format!("`{}`", part)).collect::<Vec<_>>().join("."))
+}
+
+/// Quote a table name given as text like `quote_table_name`, more than a schema and a table is an error as well.
+pub(crate) fn quote_table_str(table: &str) -> Result<String, AkitaError> {
+    if table.split('.').count() > 2 {
+        return Err(AkitaError::MissingTable(format!("Invalid table name: {}", table)));
+    }
+    quote_table_name(&TableName::from(table.trim()))
+}
+
+/// build the `CREATE TABLE IF NOT EXISTS` statement of the entity, the column types come from
+/// `#[field(sql_type = "..")]` or the mapping of the rust types, `TEXT` when neither is known
+#[cfg_attr(not(feature = "akita-ddl"), allow(dead_code))]
+pub fn build_create_table_clause<T>() -> String
+    where
+        T: GetTableName + GetFields,
+{
+    let table = T::table_name();
+    let columns = T::fields().iter().filter(|f| f.exist).map(column_definition).collect::<Vec<_>>().join(",\n\t");
+    format!("CREATE TABLE IF NOT EXISTS {} (\n\t{}\n)", table.complete_name(), columns)
+}
+
+/// the column of the DDL for a field, e.g. `` `name` VARCHAR(255) ``
+#[cfg_attr(not(feature = "akita-ddl"), allow(dead_code))]
+pub(crate) fn column_definition(field: &FieldName) -> String {
+    let sql_type = field.sql_type.to_owned().unwrap_or_else(|| "TEXT".to_string());
+    match field.field_type {
+        FieldType::TableId(_) => format!("`{}` {} PRIMARY KEY", field.name, sql_type),
+        FieldType::TableField => format!("`{}` {}", field.name, sql_type),
+    }
+}
+
+/// build the `ALTER TABLE .. ADD COLUMN` statement of a field of the entity
+#[cfg_attr(not(feature = "akita-ddl"), allow(dead_code))]
+pub fn build_add_column_clause<T>(field_name: &str) -> Result<String, AkitaError>
+    where
+        T: GetTableName + GetFields,
+{
+    let table = T::table_name();
+    let field = T::fields().into_iter().find(|f| f.exist && f.name.eq(field_name))
+        .ok_or_else(|| AkitaError::InvalidField(format!("Table({}) Unknown Column: {}", &table.name, field_name)))?;
+    if matches!(field.field_type, FieldType::TableId(_)) {
+        return Err(AkitaError::UnsupportedOperation(format!("Table({}) the primary key {} can not be added as a column", &table.name, field_name)));
+    }
+    Ok(format!("ALTER TABLE {} ADD COLUMN {}", quote_table_name(&table)?, column_definition(&field)))
+}
+
+/// build a replace clause, the insert clause with the platform's replace keyword
+#[allow(unused)]
+pub fn build_replace_clause<T>(platform: &DatabasePlatform, entities: &[&T]) -> Result<String, AkitaError>
+    where
+        T: GetTableName + GetFields + ToValue,
+{
+    let table = T::table_name();
+    if table.complete_name().is_empty() {
+        return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+    }
+    let replace: &str = match platform {
+        #[cfg(feature = "akita-mysql")]
+        DatabasePlatform::Mysql(_) => "REPLACE INTO",
+        #[cfg(feature = "akita-sqlite")]
+        DatabasePlatform::Sqlite(_) => "INSERT OR REPLACE INTO",
+        _ => return Err(AkitaError::UnsupportedOperation("The platform does not support replace".to_string())),
+    };
+    let sql = build_insert_clause(platform, entities);
+    Ok(sql.replacen("INSERT INTO", replace, 1))
+}
+
 /// build an update clause
 pub fn build_update_clause<T>(platform: &DatabasePlatform, _entity: &T, wrapper: &mut Wrapper) -> String
     where
@@ -332,21 +709,12 @@ pub fn build_update_clause<T>(platform: &DatabasePlatform, _entity: &T, wrapper:
                 .join(", ")
         );
     } else {
+        // the set values are written as is, so the placeholders of the conditions are the only ones
         sql += &format!(
             "set {}",
             set_fields
                 .iter_mut()
-                .enumerate()
-                .map(|(x, (col, _value))| {
-                    #[allow(unreachable_patterns)]
-                    match platform {
-                        #[cfg(feature = "akita-mysql")]
-                        DatabasePlatform::Mysql(_) => format!("`{}` = {}", col, _value.get_sql_segment()),
-                        #[cfg(feature = "akita-sqlite")]
-                        DatabasePlatform::Sqlite(_) => format!("`{}` = ${}", col, x + 1),
-                        _ => format!("`{}` = ${}", col, x + 1),
-                    }
-                })
+                .map(|(col, value)| format!("`{}` = {}", col, value.get_sql_segment()))
                 .collect::<Vec<_>>()
                 .join(", ")
         );
@@ -364,7 +732,7 @@ pub fn build_update_clause<T>(platform: &DatabasePlatform, _entity: &T, wrapper:
 impl AkitaMapper for AkitaEntityManager {
 
     /// Get all the table of records
-    fn list<T>(&self, mut wrapper:Wrapper) -> Result<Vec<T>, AkitaError>
+    fn list<T>(&self, wrapper:Wrapper) -> Result<Vec<T>, AkitaError>
     where
         T: GetTableName + GetFields + FromValue,
 
@@ -373,23 +741,9 @@ impl AkitaMapper for AkitaEntityManager {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let columns = T::fields();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let select_fields = wrapper.get_select_sql();
-        let enumerated_columns = if select_fields.eq("*") {
-            enumerated_columns
-        } else {
-            select_fields
-        };
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let sql = format!("SELECT {} FROM {} {}", &enumerated_columns, &table.complete_name(),where_condition);
         let mut conn = self.acquire()?;
-        let rows = conn.execute_result(&sql, Params::Nil)?;
+        let (sql, params) = list_query::<T>(&conn, wrapper);
+        let rows = conn.execute_result(&sql, params)?;
         let mut entities = vec![];
         for data in rows.iter() {
             let entity = T::from_value(&data);
@@ -399,7 +753,7 @@ impl AkitaMapper for AkitaEntityManager {
     }
 
     /// Get one the table of records
-    fn select_one<T>(&self, mut wrapper:Wrapper) -> Result<Option<T>, AkitaError>
+    fn select_one<T>(&self, wrapper:Wrapper) -> Result<Option<T>, AkitaError>
     where
         T: GetTableName + GetFields + FromValue,
     {
@@ -407,23 +761,17 @@ impl AkitaMapper for AkitaEntityManager {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let columns = T::fields();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
+        let mut conn = self.acquire()?;
+        let (mut wrapper, params) = wrapper.bind_params(&conn);
         let select_fields = wrapper.get_select_sql();
         let enumerated_columns = if select_fields.eq("*") {
-            enumerated_columns
+            T::select_columns().to_string()
         } else {
             select_fields
         };
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let sql = format!("SELECT {} FROM {} {}", &enumerated_columns, &table.complete_name(), where_condition);
-        let mut conn = self.acquire()?;
-        let rows = conn.execute_result(&sql, Params::Nil)?;
+        let (from, where_condition) = wrapper.get_from_sql(&table.complete_name());
+        let sql = format!("SELECT {} FROM {} {} {}", &enumerated_columns, from, where_condition, conn.limit_clause(0, 1));
+        let rows = conn.execute_result(&sql, params)?;
         Ok(rows.iter().next().map(|data| T::from_value(&data)))
     }
 
@@ -438,24 +786,13 @@ impl AkitaMapper for AkitaEntityManager {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
         let columns = T::fields();
-        let col_len = columns.len();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
+        let enumerated_columns = T::select_columns();
         let mut conn = self.acquire()?;
         if let Some(field) = columns.iter().find(| field| match field.field_type {
             FieldType::TableId(_) => true,
             FieldType::TableField => false,
         }) {
-            let sql = match conn {
-                #[cfg(feature = "akita-mysql")]
-                DatabasePlatform::Mysql(_) => format!("SELECT {} FROM {} WHERE `{}` = ? limit 1", &enumerated_columns, &table.complete_name(), &field.name),
-                #[cfg(feature = "akita-sqlite")]
-                DatabasePlatform::Sqlite(_) => format!("SELECT {} FROM {} WHERE `{}` = ${} limit 1", &enumerated_columns, &table.complete_name(), &field.name, col_len + 1),
-                _ => format!("SELECT {} FROM {} WHERE `{}` = ${} limit 1", &enumerated_columns, &table.complete_name(), &field.name, col_len + 1),
-            };
+            let sql = format!("SELECT {} FROM {} WHERE `{}` = ? {}", &enumerated_columns, &table.complete_name(), &field.name, conn.limit_clause(0, 1));
             let rows = conn.execute_result(&sql, (id.to_value(),).into())?;
             Ok(rows.iter().next().map(|data| T::from_value(&data)))
         } else {
@@ -464,7 +801,7 @@ impl AkitaMapper for AkitaEntityManager {
     }
 
     /// Get table of records with page
-    fn page<T>(&self, page: usize, size: usize, mut wrapper:Wrapper) -> Result<IPage<T>, AkitaError>
+    fn page<T>(&self, page: usize, size: usize, wrapper:Wrapper) -> Result<IPage<T>, AkitaError>
     where
         T: GetTableName + GetFields + FromValue,
 
@@ -473,27 +810,13 @@ impl AkitaMapper for AkitaEntityManager {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let columns = T::fields();
-        let enumerated_columns = columns
-            .iter().filter(|f| f.exist)
-            .map(|c| format!("`{}`", c.name))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let select_fields = wrapper.get_select_sql();
-        let enumerated_columns = if select_fields.eq("*") {
-            enumerated_columns
-        } else {
-            select_fields
-        };
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let count_sql = format!("select count(1) as count from {} {}", &table.complete_name(), where_condition);
-        let count: i64 = self.exec_first(&count_sql, ())?;
+        let mut conn = self.acquire()?;
+        let (count_sql, sql, params) = page_query::<T>(&conn, &self.1, wrapper);
+        let count = conn.execute_result(&count_sql, params.to_owned())?.iter().next().map(|data| i64::from_value(&data)).unwrap_or_default();
         let mut page = IPage::new(page, size ,count as usize, vec![]);
         if page.total > 0 {
-            let sql = format!("SELECT {} FROM {} {} limit {}, {}", &enumerated_columns, &table.complete_name(), where_condition,page.offset(),  page.size);
-            let mut conn = self.acquire()?;
-            let rows = conn.execute_result(&sql, Params::Nil)?;
+            let sql = format!("{} {}", sql, conn.limit_clause(page.offset(), page.size));
+            let rows = conn.execute_result(&sql, params)?;
             let mut entities = vec![];
             for dao in rows.iter() {
                 let entity = T::from_value(&dao);
@@ -504,6 +827,49 @@ impl AkitaMapper for AkitaEntityManager {
         Ok(page)
     }
 
+    /// Get table of records with page, without running the count query.
+    fn page_records<T>(&self, page: usize, size: usize, wrapper: Wrapper) -> Result<Vec<T>, AkitaError>
+        where
+            T: GetTableName + GetFields + FromValue,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        let mut conn = self.acquire()?;
+        page_records_on(&mut conn, &self.1, page, size, wrapper)
+    }
+
+    /// Get all the table of records as a shared slice.
+    fn list_arc<T>(&self, wrapper: Wrapper) -> Result<Arc<[T]>, AkitaError>
+        where
+            T: GetTableName + GetFields + FromValue,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        let mut conn = self.acquire()?;
+        list_arc_on(&mut conn, wrapper)
+    }
+
+    /// Count the records per value of `group_col`.
+    fn count_group_by<T, K>(&self, group_col: &str, wrapper: Wrapper) -> Result<HashMap<K, usize>, AkitaError>
+        where
+            T: GetTableName + GetFields,
+            K: FromValue + Eq + Hash,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        if !T::fields().iter().any(|f| f.exist && f.name.eq(group_col)) {
+            return Err(AkitaError::InvalidField(format!("Table({}) Unknown Column: {}", &table.name, group_col)));
+        }
+        let mut conn = self.acquire()?;
+        count_group_by_on::<T, K>(&mut conn, group_col, wrapper)
+    }
+
     /// Get the total count of records
     fn count<T>(&self, mut wrapper:Wrapper) -> Result<usize, AkitaError>
     where
@@ -513,14 +879,12 @@ impl AkitaMapper for AkitaEntityManager {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let where_condition = wrapper.get_sql_segment();
-        let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
-        let sql = format!(
-            "SELECT COUNT(1) AS count FROM {} {}",
-            table.complete_name(),
-            where_condition
-        );
-        self.exec_first(&sql, ())
+        let mut conn = self.acquire()?;
+        let (mut wrapper, params) = wrapper.bind_params(&conn);
+        let (from, where_condition) = wrapper.get_from_sql(&table.complete_name());
+        let sql = wrapper.get_count_sql(&from, &where_condition);
+        let count = conn.execute_result(&sql, params)?.iter().next().map(|data| i64::from_value(&data)).unwrap_or_default();
+        Ok(count as usize)
     }
 
     /// Remove the records by wrapper.
@@ -532,11 +896,12 @@ impl AkitaMapper for AkitaEntityManager {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
+        let mut conn = self.acquire()?;
+        let (mut wrapper, params) = wrapper.bind_params(&conn);
         let where_condition = wrapper.get_sql_segment();
         let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}",where_condition) };
         let sql = format!("delete from {} {}", &table.complete_name(), where_condition);
-        let mut conn = self.acquire()?;
-        let _ = conn.execute_result(&sql, Params::Nil)?;
+        let _ = conn.execute_result(&sql, params)?;
         Ok(conn.affected_rows())
     }
 
@@ -580,26 +945,11 @@ impl AkitaMapper for AkitaEntityManager {
         if table.complete_name().is_empty() {
             return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
         }
-        let mut conn = self.acquire()?;
-        let cols = T::fields();
-        let col_len = cols.len();
-        if let Some(field) = cols.iter().find(| field| match field.field_type {
-            FieldType::TableId(_) => true,
-            FieldType::TableField => false,
-        }) {
-            let sql = match conn {
-                #[cfg(feature = "akita-mysql")]
-                DatabasePlatform::Mysql(_) => format!("delete from {} where `{}` in (?)", &table.name, &field.name),
-                #[cfg(feature = "akita-sqlite")]
-                DatabasePlatform::Sqlite(_) => format!("delete from {} where `{}` in (${})", &table.name, &field.name, col_len + 1),
-                _ => format!("delete from {} where `{}` = ${}", &table.name, &field.name, col_len + 1),
-            };
-            let ids = ids.iter().map(|v| v.to_value().to_string()).collect::<Vec<String>>().join(",");
-            let _ = conn.execute_result(&sql, (ids,).into())?;
-            Ok(conn.affected_rows())
-        } else {
-            Err(AkitaError::MissingIdent(format!("Table({}) Missing Ident...", &table.name)))
+        if ids.is_empty() {
+            return Ok(0);
         }
+        let mut conn = self.acquire()?;
+        remove_by_ids_on::<T, I>(&mut conn, ids)
     }
 
 
@@ -613,6 +963,9 @@ impl AkitaMapper for AkitaEntityManager {
         }
         let mut conn = self.acquire()?;
         let columns = T::fields();
+        // the placeholders of the conditions follow those of the entity values
+        let first = if wrapper.fields_set.is_empty() { columns.iter().filter(|col| col.exist && col.field_type == FieldType::TableField).count() } else { 0 };
+        let (mut wrapper, params) = wrapper.bind_params_after(&conn, first);
         let sql = build_update_clause(&conn, entity, &mut wrapper);
         let update_fields = wrapper.fields_set;
         let mut bvalues: Vec<&Value> = Vec::new();
@@ -641,9 +994,10 @@ impl AkitaMapper for AkitaEntityManager {
                     None => values.push(Value::Nil),
                 }
             }
+            values.extend(params);
             conn.execute_result(&sql, values.into())?;
         } else {
-            conn.execute_result(&sql, Params::Nil)?;
+            conn.execute_result(&sql, params.into())?;
         }
         Ok(conn.affected_rows())
     }
@@ -725,7 +1079,7 @@ impl AkitaMapper for AkitaEntityManager {
     }
 
     #[allow(unused_variables)]
-    fn save_batch<T>(&self, entities: &[&T]) -> Result<(), AkitaError>
+    fn save_batch<T>(&self, entities: &[&T]) -> Result<u64, AkitaError>
     where
         T: GetTableName + GetFields + ToValue
     {
@@ -744,31 +1098,11 @@ impl AkitaMapper for AkitaEntityManager {
         T: GetTableName + GetFields + ToValue,
         I: FromValue,
     {
-        let columns = T::fields();
         let mut conn = self.acquire()?;
         let sql = build_insert_clause(&conn, &[entity]);
-        let data = entity.to_value();
-        let mut values: Vec<Value> = Vec::with_capacity(columns.len());
-        for col in columns.iter() {
-            let mut value = data.get_obj_value(&col.name);
-            match &col.fill {
-                None => {}
-                Some(v) => {
-                    match v.mode.as_ref() {
-                        "insert" | "default" => {
-                            value = v.value.as_ref();
-                        }
-                        _=> {}
-                    }
-                }
-            }
-            match value {
-                Some(value) => values.push(value.clone()),
-                None => values.push(Value::Nil),
-            }
-        }
-        let bvalues: Vec<&Value> = values.iter().collect();
+        let values = build_insert_values(&[entity]);
         conn.execute_result(&sql,values.into())?;
+        warn_id_overflow::<T>(&self.1, conn.last_insert_id());
         let rows: Rows = match conn {
             #[cfg(feature = "akita-mysql")]
             DatabasePlatform::Mysql(_) => conn.execute_result("SELECT LAST_INSERT_ID();", Params::Nil)?,
@@ -779,6 +1113,53 @@ impl AkitaMapper for AkitaEntityManager {
         Ok(last_insert_id)
     }
 
+    /// Save the entity and return the generated value of the named column with `RETURNING`,
+    /// for tables whose key is not the default auto-increment one.
+    fn save_returning_column<T, I>(&self, entity: &T, id_col: &str) -> Result<I, AkitaError>
+        where
+            T: GetTableName + GetFields + ToValue,
+            I: FromValue,
+    {
+        let table = T::table_name();
+        if table.complete_name().is_empty() {
+            return Err(AkitaError::MissingTable("Find Error, Missing Table Name !".to_string()))
+        }
+        if !T::fields().iter().any(|f| f.exist && f.name.eq(id_col)) {
+            return Err(AkitaError::InvalidField(format!("Table({}) Unknown Column: {}", &table.name, id_col)));
+        }
+        let mut conn = self.acquire()?;
+        #[allow(unreachable_code)]
+        let sql: String = match conn {
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => format!("{} RETURNING `{}`", build_insert_clause(&conn, &[entity]), id_col),
+            _ => return Err(AkitaError::UnsupportedOperation(format!("The platform can not return the column({}) of the inserted record", id_col))),
+        };
+        let rows = conn.execute_result(&sql, build_insert_values(&[entity]).into())?;
+        match rows.iter().next() {
+            Some(data) => Ok(I::from_value(&data)),
+            None => Err(AkitaError::DataError(format!("Column({}) was not returned", id_col))),
+        }
+    }
+
+    /// Insert or update a batch of records in multi-row statements
+    fn save_or_update_batch_on_conflict<T>(&self, entities: &[&T], update_cols: &[&str]) -> Result<u64, AkitaError>
+        where
+            T: GetTableName + GetFields + ToValue,
+    {
+        let mut conn = self.acquire()?;
+        save_batch_on_conflict(&mut conn, entities, update_cols)
+    }
+
+    fn save_replace<T>(&self, entity: &T) -> Result<u64, AkitaError>
+        where
+            T: GetTableName + GetFields + ToValue,
+    {
+        let mut conn = self.acquire()?;
+        let sql = build_replace_clause(&conn, &[entity])?;
+        conn.execute_result(&sql, build_insert_values(&[entity]).into())?;
+        Ok(conn.affected_rows())
+    }
+
     /// save or update
     fn save_or_update<T, I>(&self, entity: &T) -> Result<Option<I>, AkitaError>
         where
@@ -813,6 +1194,7 @@ impl AkitaMapper for AkitaEntityManager {
 #[cfg(test)]
 #[allow(unused)]
 mod test {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use akita_core::params;
     // use crate as akita;
 
@@ -832,6 +1214,239 @@ mod test {
         age: i32,
     }
 
+    #[derive(Debug, AkitaTable, Clone)]
+    struct Article {
+        #[table_id]
+        id: Option<i32>,
+        title: String,
+        #[field(fill(mode = "db_default"))]
+        created_at: Option<String>,
+    }
+
+    #[test]
+    fn insert_omit_db_default() {
+        use crate::GetFields;
+        use crate::manager::build_insert_values;
+        let article = Article { id: None, title: "akita".to_string(), created_at: None };
+        let insertable = Article::fields().iter().filter(|f| f.insertable()).map(|f| f.name.to_owned()).collect::<Vec<_>>();
+        assert_eq!(insertable, vec!["id".to_string(), "title".to_string()]);
+        assert_eq!(build_insert_values(&[&article]), vec![akita::Value::Nil, akita::Value::Text("akita".to_string())]);
+    }
+
+    #[derive(Debug, AkitaTable, Clone)]
+    struct Profile {
+        #[table_id]
+        id: Option<i32>,
+        nickname: Option<String>,
+        age: Option<i32>,
+    }
+
+    #[test]
+    fn insert_values_heterogeneous() {
+        use crate::manager::build_insert_values;
+        let first = Profile { id: Some(1), nickname: None, age: Some(18) };
+        let second = Profile { id: Some(2), nickname: Some("akita".to_string()), age: None };
+        assert_eq!(build_insert_values(&[&first, &second]), vec![
+            akita::Value::Int(1), akita::Value::Nil, akita::Value::Int(18),
+            akita::Value::Int(2), akita::Value::Text("akita".to_string()), akita::Value::Nil,
+        ]);
+    }
+
+    /// `rows_into_arc` of `list_arc` against `Arc::from` the `Vec` of `list`, run with
+    /// `cargo test --release list_arc_bench -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn list_arc_bench() {
+        use std::{sync::Arc, time::Instant};
+        use crate::manager::rows_into_arc;
+        let mut rows = akita::Rows::new(vec!["id".to_string(), "nickname".to_string(), "age".to_string()]);
+        for i in 0..200_000 {
+            rows.push(vec![akita::Value::Int(i), akita::Value::Text(format!("akita-{}", i)), akita::Value::Int(i % 90)]);
+        }
+        let (mut direct, mut copied) = (Vec::new(), Vec::new());
+        for _ in 0..10 {
+            let start = Instant::now();
+            let shared: Arc<[Profile]> = rows_into_arc(&rows);
+            direct.push(start.elapsed());
+            let start = Instant::now();
+            let listed: Arc<[Profile]> = Arc::from(rows.iter().map(|data| Profile::from_value(&data)).collect::<Vec<_>>());
+            copied.push(start.elapsed());
+            assert_eq!(shared.len(), listed.len());
+        }
+        direct.sort();
+        copied.sort();
+        println!("rows_into_arc: {:?}, Arc::from(list): {:?} (median of 10, 200k rows)", direct[5], copied[5]);
+    }
+
+    #[derive(Debug, AkitaTable, Clone)]
+    struct AccessLog {
+        #[table_id]
+        id: Option<i32>,
+        #[field(inet)]
+        ip: IpAddr,
+        peer: Ipv4Addr,
+    }
+
+    #[test]
+    fn inet_field() {
+        let log = AccessLog { id: None, ip: "::1".parse().unwrap(), peer: Ipv4Addr::new(10, 0, 0, 1) };
+        let data = log.to_value();
+        assert_eq!(data.get_obj_value("ip"), Some(&akita::Value::Blob(Ipv6Addr::LOCALHOST.octets().to_vec())));
+        assert_eq!(data.get_obj_value("peer"), Some(&akita::Value::Text("10.0.0.1".to_string())));
+        let restored = AccessLog::from_value(&data);
+        assert_eq!((restored.ip, restored.peer), (log.ip, log.peer));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, FromValue, ToValue)]
+    struct AccountId(i64);
+
+    #[derive(Debug, AkitaTable, Clone)]
+    #[table(name = "t_account")]
+    struct Account {
+        #[table_id]
+        id: AccountId,
+        parent_id: Option<AccountId>,
+        name: String,
+    }
+
+    #[test]
+    fn newtype_field() {
+        let account = Account { id: AccountId(7), parent_id: Some(AccountId(1)), name: "akita".to_string() };
+        let data = account.to_value();
+        assert_eq!(data.get_obj_value("id"), Some(&akita::Value::Bigint(7)));
+        assert_eq!(AccountId::from_value(&akita::Value::Bigint(7)), AccountId(7));
+        let restored = Account::from_value(&data);
+        assert_eq!((restored.id, restored.parent_id), (AccountId(7), Some(AccountId(1))));
+        let mut missing_id = akita::Value::new_object();
+        missing_id.insert_obj("name", "akita");
+        assert!(Account::from_value_opt(&missing_id).is_err());
+    }
+
+    #[derive(Debug, AkitaTable, Clone)]
+    #[table(name = "t_product")]
+    struct Product {
+        #[table_id]
+        id: Option<u64>,
+        #[field(sql_type = "VARCHAR(64)")]
+        code: String,
+        price: f64,
+        #[field(exist = false)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, AkitaTable, Clone)]
+    #[table(name = "t_org_user", after_load)]
+    struct OrgUser {
+        #[table_id]
+        id: i64,
+        org_id: Option<i64>,
+        #[field(exist = false)]
+        is_org: bool,
+    }
+
+    impl akita::AfterLoad for OrgUser {
+        fn after_load(&mut self) {
+            self.is_org = self.org_id.is_some();
+        }
+    }
+
+    #[test]
+    fn after_load_fills_derived_field() {
+        let mut data = akita::Value::new_object();
+        data.insert_obj("id", 1i64);
+        data.insert_obj("org_id", 9i64);
+        assert!(OrgUser::from_value(&data).is_org);
+        data.insert_obj_value("org_id", &akita::Value::Nil);
+        assert!(!OrgUser::from_value(&data).is_org);
+    }
+
+    #[derive(Debug, PartialEq, FromValue)]
+    struct IdRange {
+        lo: i64,
+        hi: i64,
+    }
+
+    #[test]
+    fn first_row_into_struct() {
+        // the row mapping of `exec_first`, by the column names rather than their order
+        let mut rows = akita::Rows::new(vec!["hi".to_string(), "lo".to_string()]);
+        rows.push(vec![akita::Value::Bigint(8), akita::Value::Int(3)]);
+        assert_eq!(rows.iter().next().map(|data| IdRange::from_value(&data)), Some(IdRange { lo: 3, hi: 8 }));
+        assert_eq!(rows.iter().next().map(|data| i64::from_value(&data)), Some(8));
+    }
+
+    #[test]
+    fn bulk_load_encoding() {
+        use crate::manager::{build_bulk_insert_clause, infile_row};
+        assert_eq!(build_bulk_insert_clause("t_log", &["id", "msg"], 2, false), "INSERT INTO t_log (`id`, `msg`) VALUES (?, ?), (?, ?)");
+        assert_eq!(build_bulk_insert_clause("t_log", &["id", "msg"], 2, true), "INSERT INTO t_log (`id`, `msg`) VALUES ($1, $2), ($3, $4)");
+        let mut buf = Vec::new();
+        infile_row(&[akita::Value::Int(1), akita::Value::Text("a\tb\\c\n".to_string()), akita::Value::Nil, akita::Value::Bool(true)], &mut buf);
+        assert_eq!(String::from_utf8(buf).unwrap(), "1\ta\\tb\\\\c\\n\t\\N\t1\n");
+    }
+
+    #[test]
+    fn delete_by_ids_clause() {
+        use crate::manager::build_delete_by_ids_clause;
+        assert_eq!(build_delete_by_ids_clause("t_product", "id", 3, false), "delete from t_product where `id` in (?, ?, ?)");
+        assert_eq!(build_delete_by_ids_clause("t_product", "id", 2, true), "delete from t_product where `id` in ($1, $2)");
+    }
+
+    #[test]
+    fn page_default_order() {
+        use crate::{ISegment, manager::default_page_order};
+        let cfg = AkitaConfig::new("mysql://root:127.0.0.1:3306/test".to_string());
+        let mut wrapper = default_page_order::<Product>(&cfg, Wrapper::new().eq("price", 1));
+        assert_eq!(wrapper.get_sql_segment().trim(), "(price = 1) order by id asc");
+        let mut wrapper = default_page_order::<Product>(&cfg, Wrapper::new().desc_by(vec!["price"]));
+        assert_eq!(wrapper.get_sql_segment().trim(), "(1 = 1) order by price desc");
+        let mut wrapper = default_page_order::<Product>(&cfg.set_page_default_order(false), Wrapper::new());
+        assert_eq!(wrapper.get_sql_segment().trim(), "(1 = 1)");
+    }
+
+    #[test]
+    fn quote_table_name() {
+        use crate::manager::quote_table_name;
+        assert_eq!(quote_table_name(&akita::TableName::from("db.t_user")).ok(), Some("`db`.`t_user`".to_string()));
+        assert_eq!(quote_table_name(&akita::TableName::from("t_user")).ok(), Some("`t_user`".to_string()));
+        assert!(quote_table_name(&akita::TableName::from("t_user; drop table x")).is_err());
+        use crate::manager::quote_table_str;
+        assert_eq!(quote_table_str("db.t_user").ok(), Some("`db`.`t_user`".to_string()));
+        assert!(quote_table_str("t; DROP TABLE x").is_err());
+        assert!(quote_table_str("a.b.c").is_err());
+        assert!(quote_table_str(" ").is_err());
+    }
+
+    #[test]
+    fn id_type_max() {
+        use crate::manager::id_type_max;
+        assert_eq!(id_type_max("INT UNSIGNED"), Some(u32::MAX as u64));
+        assert_eq!(id_type_max("int(11)"), Some(i32::MAX as u64));
+        assert_eq!(id_type_max("MEDIUMINT unsigned"), Some(16_777_215));
+        assert_eq!(id_type_max("BIGINT"), Some(i64::MAX as u64));
+        assert_eq!(id_type_max("VARCHAR(64)"), None);
+    }
+
+    #[test]
+    fn create_table_clause() {
+        use crate::manager::build_create_table_clause;
+        assert_eq!(build_create_table_clause::<Product>(), "CREATE TABLE IF NOT EXISTS t_product (\n\t`id` BIGINT PRIMARY KEY,\n\t`code` VARCHAR(64),\n\t`price` DOUBLE\n)");
+    }
+
+    #[test]
+    fn add_column_clause() {
+        use crate::manager::build_add_column_clause;
+        assert_eq!(build_add_column_clause::<Product>("code").ok(), Some("ALTER TABLE `t_product` ADD COLUMN `code` VARCHAR(64)".to_string()));
+        assert!(build_add_column_clause::<Product>("tags").is_err());
+        assert!(build_add_column_clause::<Product>("id").is_err());
+    }
+
+    #[test]
+    fn select_columns() {
+        use crate::GetFields;
+        assert_eq!(SystemUser::select_columns(), "`id`, `ffff`, `ssss`");
+    }
+
     #[test]
     fn get_table_info() {
         let s = params! { "test" => 1, "id" => 3, "id"=> 4};
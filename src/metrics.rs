@@ -0,0 +1,93 @@
+//!
+//! Metrics hooks.
+//!
+//! A pluggable sink for connection and query metrics, so the numbers can be fed to Prometheus or any other
+//! backend without the crate depending on one. Nothing is measured until a hook is set on the `AkitaConfig`.
+//!
+use std::{collections::hash_map::DefaultHasher, fmt, hash::{Hash, Hasher}, sync::Arc, time::{Duration, Instant}};
+
+use akita_core::comm::normalize_sql;
+
+use crate::{AkitaConfig, AkitaError, Rows};
+
+/// Callbacks invoked from the connection and execution paths, every method defaults to a no-op.
+///
+/// The callbacks run inline on the calling thread, keep them cheap, e.g. bump an atomic counter or a histogram.
+pub trait AkitaMetrics: Send + Sync {
+    /// A connection was checked out of the pool after waiting `wait`.
+    fn on_acquire(&self, _wait: Duration) {}
+    /// A statement finished, `sql_hash` identifies its shape (the sql with the literals stripped),
+    /// `rows` is the number of rows it returned.
+    fn on_query(&self, _sql_hash: u64, _elapsed: Duration, _rows: usize) {}
+    /// Checking out a connection or executing a statement failed.
+    fn on_error(&self, _err: &AkitaError) {}
+}
+
+#[derive(Clone)]
+pub(crate) struct MetricsHook(pub(crate) Arc<dyn AkitaMetrics>);
+
+impl fmt::Debug for MetricsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsHook")
+    }
+}
+
+/// The hash passed to `on_query`, equal for statements differing only in their literals.
+pub fn sql_hash(sql: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    normalize_sql(sql).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Start timing, `None` when no hook is set so the default costs nothing.
+pub(crate) fn start(cfg: &AkitaConfig) -> Option<Instant> {
+    cfg.metrics().map(|_| Instant::now())
+}
+
+pub(crate) fn record_acquire<T>(cfg: &AkitaConfig, start: Option<Instant>, result: &Result<T, AkitaError>) {
+    if let (Some(hook), Some(start)) = (cfg.metrics(), start) {
+        match result {
+            Ok(_) => hook.on_acquire(start.elapsed()),
+            Err(err) => hook.on_error(err),
+        }
+    }
+}
+
+#[cfg_attr(not(any(feature = "akita-mysql", feature = "akita-sqlite")), allow(dead_code))]
+pub(crate) fn record_query(cfg: &AkitaConfig, sql: &str, start: Option<Instant>, result: &Result<Rows, AkitaError>) {
+    if let (Some(hook), Some(start)) = (cfg.metrics(), start) {
+        match result {
+            Ok(rows) => hook.on_query(sql_hash(sql), start.elapsed(), rows.data.len()),
+            Err(err) => hook.on_error(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+    use std::time::Duration;
+
+    use crate::{AkitaConfig, AkitaError, AkitaMetrics};
+    use crate::metrics::{record_acquire, sql_hash, start};
+
+    #[derive(Default)]
+    struct Counter { acquired: AtomicUsize, failed: AtomicUsize }
+
+    impl AkitaMetrics for Counter {
+        fn on_acquire(&self, _wait: Duration) { self.acquired.fetch_add(1, Ordering::SeqCst); }
+        fn on_error(&self, _err: &AkitaError) { self.failed.fetch_add(1, Ordering::SeqCst); }
+    }
+
+    #[test]
+    fn metrics_hook() {
+        assert_eq!(sql_hash("select * from t_user where id = 1"), sql_hash("select * from t_user where id = 2"));
+        assert!(start(&AkitaConfig::new("mysql://root@localhost/test".to_string())).is_none());
+        let counter = Arc::new(Counter::default());
+        let cfg = AkitaConfig::new("mysql://root@localhost/test".to_string()).set_metrics_hook(counter.clone());
+        record_acquire(&cfg, start(&cfg), &Ok(()));
+        record_acquire::<()>(&cfg, start(&cfg), &Err(AkitaError::UnknownDatabase("down".to_string())));
+        assert_eq!(counter.acquired.load(Ordering::SeqCst), 1);
+        assert_eq!(counter.failed.load(Ordering::SeqCst), 1);
+    }
+}
@@ -2,10 +2,10 @@
 //! MySQL modules.
 //!
 use mysql::prelude::Protocol;
-use mysql::{Conn, Error, Opts, OptsBuilder, Row, prelude::Queryable};
+use mysql::{Conn, Error, LocalInfileHandler, Opts, OptsBuilder, Row, prelude::Queryable};
 use r2d2::{ManageConnection, Pool};
 
-use std::result::Result;
+use std::{io::Write, result::Result, sync::Arc};
 use akita_core::Array;
 
 use crate::{AkitaConfig, Params, self as akita};
@@ -13,10 +13,11 @@ use crate::{AkitaConfig, Params, self as akita};
 cfg_if! {if #[cfg(feature = "akita-auth")]{
     use crate::auth::{GrantUserPrivilege, Role, UserInfo, DataBaseUser};
 }}
-use crate::database::Database;
+use crate::database::{Database, check_param_count, row_object};
 use crate::pool::LogLevel;
+use crate::metrics;
 use serde_json::Map;
-use crate::{ToValue, Value, FromValue, Rows, SqlType, cfg_if, AkitaError, ColumnDef, FieldName, ColumnSpecification, DatabaseName, TableDef, TableName, SchemaContent, comm};
+use crate::{ToValue, Value, FromValue, Rows, ColumnKind, SqlType, cfg_if, AkitaError, ColumnDef, FieldName, ColumnSpecification, DatabaseName, TableDef, TableName, SchemaContent, comm};
 type R2d2Pool = Pool<MysqlConnectionManager>;
 
 #[derive(Debug)]
@@ -27,6 +28,23 @@ impl MysqlDatabase {
         MysqlDatabase(pool, cfg)
     }
 
+    /// the thread id of the connection on the server
+    pub fn connection_id(&self) -> u32 {
+        self.0.connection_id()
+    }
+
+    /// Run a `LOAD DATA LOCAL INFILE` statement with `data` as the content of the file rather than reading it
+    /// from the disk, returns the number of rows loaded.
+    pub fn load_local_infile(&mut self, sql: &str, data: Vec<u8>) -> Result<u64, AkitaError> {
+        self.log(format!("Prepare SQL: {} with {} bytes of data", sql, data.len()));
+        let data = Arc::new(data);
+        self.0.set_local_infile_handler(Some(LocalInfileHandler::new(move |_file_name, writer| writer.write_all(&data))));
+        let result = self.0.query_drop(sql);
+        self.0.set_local_infile_handler(None);
+        result?;
+        Ok(self.0.affected_rows())
+    }
+
     pub fn log(&self, _fmt: String) {
         if let Some(log_level) = &self.1.log_level() {
             match log_level {
@@ -51,24 +69,14 @@ impl MysqlDatabase {
             }
         }
     }
-}
-
-/// MYSQL数据操作
-impl Database for MysqlDatabase {
-    fn start_transaction(&mut self) -> Result<(), AkitaError> {
-        self.execute_result("BEGIN", Params::Nil).map(|_| ()).map_err(AkitaError::from)
-    }
-
-    fn commit_transaction(&mut self) -> Result<(), AkitaError> {
-        self.execute_result("COMMIT", Params::Nil).map(|_| ()).map_err(AkitaError::from)
-    }
 
-    fn rollback_transaction(&mut self) -> Result<(), AkitaError> {
-        self.execute_result("ROLLBACK", Params::Nil).map(|_| ()).map_err(AkitaError::from)
-    }
-    
-    fn execute_result(&mut self, sql: &str, param: Params) -> Result<Rows, AkitaError> {
-        self.log(format!("Prepare SQL: {} params: {:?}", &sql, param));
+    fn execute_rows(&mut self, sql: &str, param: Params) -> Result<Rows, AkitaError> {
+        self.log(format!("Prepare SQL: {} params: {:?}", self.1.log_sql(sql), param));
+        check_param_count(sql, &param)?;
+        #[cfg(feature = "akita-detect")]
+        crate::detector::record_query(sql);
+        #[cfg(feature = "akita-debug")]
+        crate::debug::record_last_query(sql, &param);
         fn collect<T: Protocol>(mut rows: mysql::QueryResult<T>) -> Result<Rows, AkitaError> {
             let column_types: Vec<_> = rows.columns().as_ref().iter().map(|c| c.column_type()).collect();
             let fields = rows
@@ -79,6 +87,7 @@ impl Database for MysqlDatabase {
                 .map_err(|e| AkitaError::from(e))?;
 
             let mut records = Rows::new(fields);
+            records.set_column_kinds(column_types.iter().map(column_kind).collect());
             // while rows.next().is_some() {
             //     for r in rows.by_ref() {
             //         records.push(into_record(r.map_err(AkitaError::from)?, &column_types)?);
@@ -143,9 +152,77 @@ impl Database for MysqlDatabase {
             },
         }
     }
+}
+
+/// MYSQL数据操作
+impl Database for MysqlDatabase {
+    fn start_transaction(&mut self) -> Result<(), AkitaError> {
+        self.execute_result("BEGIN", Params::Nil).map(|_| ()).map_err(AkitaError::from)
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), AkitaError> {
+        self.execute_result("COMMIT", Params::Nil).map(|_| ()).map_err(AkitaError::from)
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), AkitaError> {
+        self.execute_result("ROLLBACK", Params::Nil).map(|_| ()).map_err(AkitaError::from)
+    }
+    
+    fn execute_result(&mut self, sql: &str, param: Params) -> Result<Rows, AkitaError> {
+        let start = metrics::start(&self.1);
+        let result = self.execute_rows(sql, param);
+        metrics::record_query(&self.1, sql, start, &result);
+        result
+    }
     
+    fn for_each_row(&mut self, sql: &str, param: Params, f: &mut dyn FnMut(usize, &Value) -> Result<(), AkitaError>) -> Result<usize, AkitaError> {
+        self.log(format!("Prepare SQL: {} params: {:?}", self.1.log_sql(sql), param));
+        check_param_count(sql, &param)?;
+        // the rows are read from the connection one at a time, the unread ones are drained when stopping early
+        fn each<T: Protocol>(mut rows: mysql::QueryResult<T>, f: &mut dyn FnMut(usize, &Value) -> Result<(), AkitaError>) -> Result<usize, AkitaError> {
+            let column_types: Vec<_> = rows.columns().as_ref().iter().map(|c| c.column_type()).collect();
+            let columns = rows
+                .columns().as_ref()
+                .iter()
+                .map(|c| std::str::from_utf8(c.name_ref()).map(ToString::to_string))
+                .collect::<Result<Vec<String>, _>>()
+                .map_err(AkitaError::from)?;
+            let mut count = 0;
+            for r in rows.by_ref() {
+                let record = into_record(r.map_err(AkitaError::from)?, &column_types)?;
+                f(count, &row_object(&columns, &record))?;
+                count += 1;
+            }
+            Ok(count)
+        }
+        let param = match param {
+            Params::Nil => {
+                let rows = self.0.query_iter(sql).map_err(|e| AkitaError::ExcuteSqlError(e.to_string(), sql.to_string()))?;
+                return each(rows, f);
+            }
+            Params::Vector(param) => param,
+            Params::Custom(param) => {
+                let len = sql.len();
+                let mut values = param.into_iter().map(|(name, value)| (sql.find(&format!(":{}", name)).unwrap_or(len), value)).collect::<Vec<_>>();
+                values.sort_by(|a, b| a.0.cmp(&b.0));
+                values.into_iter().map(|v| v.1).collect()
+            }
+        };
+        let stmt = self.0.prep(sql).map_err(|e| AkitaError::ExcuteSqlError(e.to_string(), sql.to_string()))?;
+        let params: mysql::Params = param
+            .iter()
+            .map(|v| MySQLValue(v))
+            .map(|v| mysql::prelude::ToValue::to_value(&v))
+            .collect::<Vec<_>>()
+            .into();
+        let rows = self.0.exec_iter(stmt, &params).map_err(|e| AkitaError::ExcuteSqlError(e.to_string(), sql.to_string()))?;
+        each(rows, f)
+    }
+
     fn execute_drop(&mut self, sql: &str, param: Params) -> Result<(), AkitaError> {
-        self.log(format!("Prepare SQL: {} params: {:?}", &sql, param));
+        self.log(format!("Prepare SQL: {} params: {:?}", self.1.log_sql(sql), param));
+        #[cfg(feature = "akita-debug")]
+        crate::debug::record_last_query(sql, &param);
         match param {
             Params::Nil => {
                 self
@@ -335,8 +412,11 @@ impl Database for MysqlDatabase {
     }
 
     fn exist_table(&mut self, table_name: &TableName) -> Result<bool, AkitaError> {
-        let sql = "SELECT count(1) as count FROM information_schema.tables WHERE TABLE_SCHEMA = ? and TABLE_NAME = ?";
-        self.execute_result(&sql, (&table_name.name, &table_name.schema).into()).map(|rows| {
+        let (sql, params): (&str, Params) = match table_name.schema {
+            Some(ref schema) => ("SELECT count(1) as count FROM information_schema.tables WHERE TABLE_SCHEMA = ? and TABLE_NAME = ?", (schema, &table_name.name).into()),
+            None => ("SELECT count(1) as count FROM information_schema.tables WHERE TABLE_SCHEMA = DATABASE() and TABLE_NAME = ?", (&table_name.name,).into()),
+        };
+        self.execute_result(sql, params).map(|rows| {
             rows.iter().next()
                 .map(|row| {
                     row.get_obj_opt::<i32>("count")
@@ -724,6 +804,39 @@ impl mysql::prelude::ToValue for MySQLValue<'_> {
     }
 }
 
+/// the coarse column type of a mysql column
+fn column_kind(column_type: &mysql::consts::ColumnType) -> ColumnKind {
+    use mysql::consts::ColumnType;
+    match column_type {
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => ColumnKind::Decimal,
+        ColumnType::MYSQL_TYPE_TINY
+        | ColumnType::MYSQL_TYPE_SHORT
+        | ColumnType::MYSQL_TYPE_YEAR
+        | ColumnType::MYSQL_TYPE_LONG
+        | ColumnType::MYSQL_TYPE_INT24
+        | ColumnType::MYSQL_TYPE_LONGLONG => ColumnKind::Integer,
+        ColumnType::MYSQL_TYPE_FLOAT | ColumnType::MYSQL_TYPE_DOUBLE => ColumnKind::Float,
+        ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => ColumnKind::Date,
+        ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2 => ColumnKind::Time,
+        ColumnType::MYSQL_TYPE_TIMESTAMP
+        | ColumnType::MYSQL_TYPE_TIMESTAMP2
+        | ColumnType::MYSQL_TYPE_DATETIME
+        | ColumnType::MYSQL_TYPE_DATETIME2 => ColumnKind::DateTime,
+        ColumnType::MYSQL_TYPE_VARCHAR
+        | ColumnType::MYSQL_TYPE_VAR_STRING
+        | ColumnType::MYSQL_TYPE_STRING
+        | ColumnType::MYSQL_TYPE_ENUM
+        | ColumnType::MYSQL_TYPE_SET => ColumnKind::Text,
+        ColumnType::MYSQL_TYPE_JSON => ColumnKind::Json,
+        ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_BLOB
+        | ColumnType::MYSQL_TYPE_BIT => ColumnKind::Bytes,
+        ColumnType::MYSQL_TYPE_NULL | ColumnType::MYSQL_TYPE_GEOMETRY => ColumnKind::Unknown,
+    }
+}
+
 fn into_record(
     mut row: mysql::Row,
     column_types: &[mysql::consts::ColumnType],
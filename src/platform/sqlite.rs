@@ -15,8 +15,9 @@ cfg_if! {if #[cfg(feature = "auth")]{
 }}
 
 use crate::{AkitaConfig, Params, ToValue};
-use crate::database::Database;
+use crate::database::{Database, check_param_count, row_object};
 use crate::pool::LogLevel;
+use crate::metrics;
 use crate::{self as akita, comm::{extract_datatype_with_capacity, maybe_trim_parenthesis}, Rows, Value, SqlType, cfg_if, Capacity, ColumnConstraint, ForeignKey, Key, Literal, TableKey, AkitaError, ColumnDef, FieldName, ColumnSpecification, DatabaseName, TableDef, TableName, SchemaContent};
 type R2d2Pool = Pool<SqliteConnectionManager>;
 
@@ -51,25 +52,14 @@ impl SqliteDatabase {
             }
         }
     }
-}
-
-/// SQLite数据操作
-#[allow(unused)]
-impl Database for SqliteDatabase {
-    fn start_transaction(&mut self) -> Result<(), AkitaError> {
-        self.execute_result("BEGIN TRANSACTION", Params::Nil).map(|_| ()).map_err(AkitaError::from)
-    }
-
-    fn commit_transaction(&mut self) -> Result<(), AkitaError> {
-        self.execute_result("COMMIT TRANSACTION", Params::Nil).map(|_| ()).map_err(AkitaError::from)
-    }
 
-    fn rollback_transaction(&mut self) -> Result<(), AkitaError> {
-        self.execute_result("ROLLBACK TRANSACTION", Params::Nil).map(|_| ()).map_err(AkitaError::from)
-    }
-    
-    fn execute_result(&mut self, sql: &str, params: Params) -> Result<Rows, AkitaError> {
-        self.log(format!("Prepare SQL: {} params: {:?}", &sql, param));
+    fn execute_rows(&mut self, sql: &str, params: Params) -> Result<Rows, AkitaError> {
+        self.log(format!("Prepare SQL: {} params: {:?}", self.1.log_sql(sql), param));
+        check_param_count(sql, &params)?;
+        #[cfg(feature = "akita-detect")]
+        crate::detector::record_query(sql);
+        #[cfg(feature = "akita-debug")]
+        crate::debug::record_last_query(sql, &params);
         let stmt = self.0.prepare(&sql);
         let column_names = if let Ok(ref stmt) = stmt {
             stmt.column_names()
@@ -126,15 +116,42 @@ impl Database for SqliteDatabase {
                         records.push(record);
                     }
                 }
+                // sqlite is dynamically typed, the column types come from the values
+                records.infer_column_kinds();
                 self.log(format!("AffectRows: {} records: {:?}", records.len(), rows));
                 Ok(records)
             }
             Err(e) => Err(AkitaError::from(e)),
         }
     }
+}
+
+/// SQLite数据操作
+#[allow(unused)]
+impl Database for SqliteDatabase {
+    fn start_transaction(&mut self) -> Result<(), AkitaError> {
+        self.execute_result("BEGIN TRANSACTION", Params::Nil).map(|_| ()).map_err(AkitaError::from)
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), AkitaError> {
+        self.execute_result("COMMIT TRANSACTION", Params::Nil).map(|_| ()).map_err(AkitaError::from)
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), AkitaError> {
+        self.execute_result("ROLLBACK TRANSACTION", Params::Nil).map(|_| ()).map_err(AkitaError::from)
+    }
+    
+    fn execute_result(&mut self, sql: &str, params: Params) -> Result<Rows, AkitaError> {
+        let start = metrics::start(&self.1);
+        let result = self.execute_rows(sql, params);
+        metrics::record_query(&self.1, sql, start, &result);
+        result
+    }
 
     fn execute_drop(&mut self, sql: &str, params: Params) -> Result<(), AkitaError> {
-        self.log(format!("Prepare SQL: {} params: {:?}", &sql, param));
+        self.log(format!("Prepare SQL: {} params: {:?}", self.1.log_sql(sql), param));
+        #[cfg(feature = "akita-debug")]
+        crate::debug::record_last_query(sql, &params);
         let stmt = self.0.prepare(&sql);
         match stmt {
             Ok(mut stmt) => {
@@ -169,6 +186,70 @@ impl Database for SqliteDatabase {
         }
     }
 
+    fn for_each_row(&mut self, sql: &str, params: Params, f: &mut dyn FnMut(usize, &Value) -> Result<(), AkitaError>) -> Result<usize, AkitaError> {
+        self.log(format!("Prepare SQL: {} params: {:?}", self.1.log_sql(sql), params));
+        check_param_count(sql, &params)?;
+        let sql_values = match params {
+            Params::Nil => vec![],
+            Params::Vector(param) => param.iter().map(to_sq_value).collect::<Vec<_>>(),
+            Params::Custom(param) => {
+                let len = sql.len();
+                let mut values = param.iter().map(|param| (sql.find(&format!(":{}", param.0)).unwrap_or(len), &param.1)).collect::<Vec<_>>();
+                values.sort_by(|a, b| a.0.cmp(&b.0));
+                values.iter().map(|v| to_sq_value(v.1)).collect::<Vec<_>>()
+            },
+        };
+        let mut stmt = self.0.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().iter().map(ToString::to_string).collect();
+        let mut rows = stmt.query(sql_values)?;
+        let mut count = 0;
+        // sqlite steps to the next row only when asked, nothing past the current row is held
+        while let Some(row) = rows.next()? {
+            let mut record: Vec<Value> = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                record.push(match row.get(i)? {
+                    rusqlite::types::Value::Blob(v) => Value::Blob(v),
+                    rusqlite::types::Value::Real(v) => Value::Double(v),
+                    rusqlite::types::Value::Integer(v) => Value::Bigint(v),
+                    rusqlite::types::Value::Text(v) => Value::Text(v),
+                    rusqlite::types::Value::Null => Value::Nil,
+                });
+            }
+            f(count, &row_object(&columns, &record))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn stream_blob(&mut self, sql: &str, params: Params, writer: &mut dyn std::io::Write) -> Result<u64, AkitaError> {
+        self.log(format!("Prepare SQL: {} params: {:?}", self.1.log_sql(sql), params));
+        check_param_count(sql, &params)?;
+        let sql_values = match params {
+            Params::Nil => vec![],
+            Params::Vector(param) => param.iter().map(to_sq_value).collect::<Vec<_>>(),
+            Params::Custom(param) => {
+                let len = sql.len();
+                let mut values = param.iter().map(|param| (sql.find(&format!(":{}", param.0)).unwrap_or(len), &param.1)).collect::<Vec<_>>();
+                values.sort_by(|a, b| a.0.cmp(&b.0));
+                values.iter().map(|v| to_sq_value(v.1)).collect::<Vec<_>>()
+            },
+        };
+        let mut stmt = self.0.prepare(sql)?;
+        if stmt.column_count() != 1 {
+            return Err(AkitaError::DataError(format!("stream_blob expects a single column, got {}", stmt.column_count())));
+        }
+        let mut rows = stmt.query(sql_values)?;
+        // borrow the value from sqlite's buffer instead of copying it into a `Value`
+        match rows.next()? {
+            Some(row) => match row.get_ref(0)? {
+                rusqlite::types::ValueRef::Blob(bytes) | rusqlite::types::ValueRef::Text(bytes) => crate::database::write_blob(writer, bytes),
+                rusqlite::types::ValueRef::Null => Ok(0),
+                v => Err(AkitaError::DataError(format!("stream_blob expects a BLOB column, got {:?}", v.data_type()))),
+            },
+            None => Ok(0),
+        }
+    }
+
     fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, AkitaError> {
         #[derive(Debug)]
         struct ColumnSimple {
@@ -394,7 +475,18 @@ impl Database for SqliteDatabase {
     }
 
     fn exist_table(&mut self, table_name: &TableName) -> Result<bool, AkitaError> {
-        todo!()
+        // an attached database keeps its own sqlite_master, the schema is quoted with the backticks within doubled
+        let sql = match table_name.schema {
+            Some(ref schema) => format!("SELECT count(1) as count FROM `{}`.sqlite_master WHERE type = 'table' AND name = ?", schema.replace('`', "``")),
+            None => "SELECT count(1) as count FROM sqlite_master WHERE type = 'table' AND name = ?".to_string(),
+        };
+        self.execute_result(&sql, (&table_name.name,).into()).map(|rows| {
+            rows.iter().next()
+                .map(|row| {
+                    row.get_obj_opt::<i64>("count")
+                        .expect("must not error")
+                }).unwrap_or_default().unwrap_or_default() > 0
+        })
     }
 
     fn get_grouped_tables(&mut self) -> Result<Vec<SchemaContent>, AkitaError> {
@@ -469,11 +561,11 @@ impl Database for SqliteDatabase {
     }
 
     fn affected_rows(&self) -> u64 {
-        todo!()
+        self.0.changes() as u64
     }
 
     fn last_insert_id(&self) -> u64 {
-        todo!()
+        self.0.last_insert_rowid() as u64
     }
 
     fn create_database(&mut self, _database: &str) -> Result<(), AkitaError> {
@@ -806,7 +898,8 @@ fn test_connection(database_url: &str) -> Result<(), AkitaError> {
 
 #[cfg(test)]
 mod test {
-    use crate::{AkitaConfig, AkitaMapper, FromValue, Pool, QueryWrapper, AkitaTable, ToValue, types::SqlType::{Int, Text, Timestamp}};
+    use std::collections::HashMap;
+    use crate::{AkitaConfig, AkitaMapper, FromValue, Pool, QueryWrapper, Wrapper, AkitaTable, ToValue, ColumnKind, Params, types::SqlType::{Int, Text, Timestamp}};
 
     #[derive(Debug, FromValue, ToValue, AkitaTable, Clone)]
     #[table(name="test")]
@@ -824,6 +917,86 @@ mod test {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn column_info() {
+        let db_url = "sqlite://./../../example/akita.sqlite3";
+        let mut pool = Pool::new(AkitaConfig::new(db_url.to_string())).unwrap();
+        let mut em = pool.entity_manager().unwrap();
+        let rows = em.execute_result("SELECT a, b AS c FROM (SELECT 1 AS a, 'akita' AS b)", Params::Nil).unwrap();
+        let columns = rows.column_info().iter().map(|c| (c.name.as_str(), c.column_type)).collect::<Vec<_>>();
+        assert_eq!(columns, vec![("a", ColumnKind::Integer), ("c", ColumnKind::Text)]);
+    }
+
+    #[test]
+    fn save_batch_affected_rows() {
+        let db_url = "sqlite://./../../example/akita.sqlite3";
+        let mut pool = Pool::new(AkitaConfig::new(db_url.to_string())).unwrap();
+        let mut em = pool.entity_manager().unwrap();
+        em.execute_result("DELETE FROM test WHERE id IN (9001, 9002)", Params::Nil).unwrap();
+        let datas = vec![TestSqlite { id: 9001, name: "a".to_string() }, TestSqlite { id: 9002, name: "b".to_string() }];
+        let affected_rows = em.save_batch(&datas.iter().collect::<Vec<_>>()).unwrap();
+        em.execute_result("DELETE FROM test WHERE id IN (9001, 9002)", Params::Nil).unwrap();
+        assert_eq!(affected_rows, datas.len() as u64);
+    }
+
+    #[derive(Debug, FromValue, PartialEq)]
+    struct IdRange {
+        lo: i64,
+        hi: i64,
+    }
+
+    #[test]
+    fn exec_first_struct() {
+        let db_url = "sqlite://./../../example/akita.sqlite3";
+        let mut pool = Pool::new(AkitaConfig::new(db_url.to_string())).unwrap();
+        let em = pool.entity_manager().unwrap();
+        let range: IdRange = em.exec_first("SELECT min(v) AS lo, max(v) AS hi FROM (SELECT 3 AS v UNION SELECT 8)", ()).unwrap();
+        assert_eq!(range, IdRange { lo: 3, hi: 8 });
+    }
+
+    #[test]
+    fn exec_first_null_aggregate() {
+        let db_url = "sqlite://./../../example/akita.sqlite3";
+        let mut pool = Pool::new(AkitaConfig::new(db_url.to_string())).unwrap();
+        let em = pool.entity_manager().unwrap();
+        let sql = "SELECT max(v) FROM (SELECT 1 AS v) WHERE v > 1";
+        assert!(em.exec_first::<i64, _, _>(sql, ()).is_err());
+        assert_eq!(em.exec_first::<Option<i64>, _, _>(sql, ()).unwrap(), None);
+        assert_eq!(em.exec_first::<i64, _, _>("SELECT count(v) FROM (SELECT 1 AS v) WHERE v > 1", ()).unwrap(), 0);
+    }
+
+    #[test]
+    fn count_group_by() {
+        let db_url = "sqlite://./../../example/akita.sqlite3";
+        let mut pool = Pool::new(AkitaConfig::new(db_url.to_string())).unwrap();
+        let em = pool.entity_manager().unwrap();
+        let counts: HashMap<String, usize> = em.count_group_by::<TestSqlite, String>("name", Wrapper::new()).unwrap();
+        assert_eq!(counts.values().sum::<usize>(), em.count::<TestSqlite>(Wrapper::new()).unwrap());
+        assert!(em.count_group_by::<TestSqlite, String>("missing", Wrapper::new()).is_err());
+    }
+
+    #[test]
+    fn remove_by_ids_binds_each_id() {
+        let db_url = "sqlite://./../../example/akita.sqlite3";
+        let mut pool = Pool::new(AkitaConfig::new(db_url.to_string())).unwrap();
+        let em = pool.entity_manager().unwrap();
+        em.exec_drop("DELETE FROM test WHERE id IN (9001, 9002, 9003)", ()).unwrap();
+        em.exec_drop("INSERT INTO test (id, name) VALUES (9001, 'a'), (9002, 'b'), (9003, 'c')", ()).unwrap();
+        assert_eq!(em.remove_by_ids::<TestSqlite, i32>(vec![9001, 9003]).unwrap(), 2);
+        assert_eq!(em.remove_by_ids::<TestSqlite, i32>(vec![]).unwrap(), 0);
+        assert_eq!(em.count::<TestSqlite>(Wrapper::new().eq("id", 9002)).unwrap(), 1);
+    }
+
+    #[test]
+    fn exec_iter_columns() {
+        let db_url = "sqlite://./../../example/akita.sqlite3";
+        let mut pool = Pool::new(AkitaConfig::new(db_url.to_string())).unwrap();
+        let em = pool.entity_manager().unwrap();
+        let (columns, rows) = em.exec_iter_with_columns("SELECT 2 AS b, 1 AS a, 3 AS c", ()).unwrap();
+        assert_eq!(columns, vec!["b", "a", "c"]);
+        assert_eq!(rows.data.len(), 1);
+    }
+
     #[test]
     fn test_list() {
         let db_url = "sqlite://./../../example/akita.sqlite3";
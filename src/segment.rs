@@ -1,7 +1,7 @@
 //! 
 //! SQL Segments.
 //! 
-use crate::{comm::*, Wrapper};
+use crate::{comm::*, Value, Wrapper};
 use chrono::{NaiveDate, NaiveDateTime};
 
 /// Segment are generally not used directly unless you are using the
@@ -31,6 +31,12 @@ pub enum Segment{
     U64(u64),
     Wrapper(Box<Wrapper>),
     Str(&'static str),
+    /// a text value, rendered within single quotes
+    Quoted(String),
+    /// segments rendered within brackets, e.g. a negated predicate or a sub query
+    Group(Vec<Segment>),
+    /// values rendered within brackets and separated by commas, e.g. the values of `in`
+    List(Vec<Segment>),
     Nil,
 }
 
@@ -196,6 +202,9 @@ impl ISegment for Segment {
                 }
             },
             Segment::Wrapper(w) => w.get_sql_segment(),
+            Segment::Quoted(val) => format!("'{}'", val.replace(SINGLE_QUOTE, EMPTY)),
+            Segment::Group(segs) => LEFT_BRACKET.to_string() + segs.iter_mut().map(|seg| seg.get_sql_segment().trim().to_string()).collect::<Vec<String>>().join(SPACE).as_str() + RIGHT_BRACKET,
+            Segment::List(segs) => LEFT_BRACKET.to_string() + segs.iter_mut().map(|seg| seg.get_sql_segment()).collect::<Vec<String>>().join(COMMA).as_str() + RIGHT_BRACKET,
         }
     }
 }
@@ -271,7 +280,27 @@ impl ToSegment for &str
         if self.is_empty() {
             return Segment::Nil
         }
-        Segment::Extenssion(format!("'{}'", self.replace(SINGLE_QUOTE, EMPTY)))
+        Segment::Quoted(self.to_string())
+    }
+}
+
+impl ToSegment for Value
+{
+    fn to_segment(&self) -> Segment {
+        match self {
+            Value::Nil => Segment::Nil,
+            Value::Bool(v) => Segment::Boolean(*v),
+            Value::Tinyint(v) => Segment::Int8(*v),
+            Value::Smallint(v) => Segment::Int16(*v),
+            Value::Int(v) => Segment::Int32(*v),
+            Value::Bigint(v) => Segment::Int64(*v),
+            Value::Float(v) => Segment::Float(*v as f64),
+            Value::Double(v) => Segment::Float(*v),
+            Value::Text(v) => Segment::Quoted(v.to_owned()),
+            Value::Date(v) => Segment::Date(*v),
+            Value::DateTime(v) => Segment::DateTime(*v),
+            other => Segment::Quoted(other.to_string()),
+        }
     }
 }
 
@@ -288,7 +317,7 @@ impl ToSegment for String
         if self.is_empty() {
             return Segment::Nil
         }
-        Segment::Extenssion(format!("'{}'", self.replace(SINGLE_QUOTE, EMPTY)))
+        Segment::Quoted(self.to_owned())
     }
 }
 
@@ -428,7 +457,7 @@ impl SegmentList {
     fn transform_list(&mut self, seg_type: &SegmentType, list: &mut Vec<Segment>, first_segment: Option<&Segment>, _last_segment: Option<&Segment>) -> bool {
         match seg_type {
             SegmentType::GroupBy => { list.remove(0); true },
-            SegmentType::Having => { if !list.is_empty() { list.push(SqlKeyword::AND.into()); } list.remove(0); true },
+            SegmentType::Having => { list.remove(0); if !self.segments.is_empty() { list.insert(0, SqlKeyword::AND.into()); } true },
             SegmentType::OrderBy => { 
                 list.remove(0);
                 if !self.segments.is_empty() {
@@ -468,16 +497,19 @@ impl SegmentList {
                         return false;
                     }
                 } else {
-                    if !self.execute_not {
-                        list.insert(if MatchSegment::EXISTS.matches(first) { 0 } else { 1 }, SqlKeyword::NOT.into());
-                        self.execute_not = true;
-                    }
                     if MatchSegment::APPLY.matches(first) {
                         list.remove(0);
                     }
                     if MatchSegment::BRACKET.matches(first) {
                         list.remove(0);
                     }
+                    if !self.execute_not {
+                        // negate only this predicate as a whole, e.g. `not (name like '%x%')`
+                        let predicate = std::mem::take(list);
+                        list.push(SqlKeyword::NOT.into());
+                        list.push(Segment::Group(predicate));
+                        self.execute_not = true;
+                    }
                     if !MatchSegment::AND_OR.matches(last) && !self.segments.is_empty() {
                         self.segments.push(SqlKeyword::AND.into());
                     }
@@ -513,12 +545,12 @@ impl ISegment for MergeSegments {
     fn get_sql_segment(&mut self) -> String {
         if self.normal.is_empty() {
             if !self.group_by.is_empty() || !self.order_by.is_empty() {
-                "(1 = 1)".to_string() + self.having.get_sql_segment().as_str() + self.order_by.get_sql_segment().as_str()
+                "(1 = 1)".to_string() + self.group_by.get_sql_segment().as_str() + self.having_sql().as_str() + self.order_by.get_sql_segment().as_str()
             } else {
                 "".to_string()
             }
         } else {
-            self.normal.get_sql_segment() + self.group_by.get_sql_segment().as_str() + self.having.get_sql_segment().as_str() + self.order_by.get_sql_segment().as_str()
+            self.normal.get_sql_segment() + self.group_by.get_sql_segment().as_str() + self.having_sql().as_str() + self.order_by.get_sql_segment().as_str()
         }
     }
 }
@@ -559,20 +591,53 @@ impl MergeSegments {
         self.having.clear();
     }
 
+    /// the having clause, which is dropped without a group by
+    pub fn having_sql(&mut self) -> String {
+        if self.group_by.is_empty() { String::default() } else { self.having.get_sql_segment() }
+    }
+
+    /// clear the where conditions only
+    pub fn clear_normal(&mut self) {
+        self.normal.clear();
+    }
+
 }
 
 impl SqlLike {
-    pub fn concat_like(&self, mut val:Segment) -> Segment {
+    /// The pattern matching the value literally, `%` and `_` within the value are escaped with `!`,
+    /// which needs an `escape '!'` after the pattern, see `needs_escape`.
+    pub fn concat_like(&self, val:Segment) -> Segment {
         if val.eq(&Segment::Nil) {
             return Segment::Nil;
         }
-        let val = val.get_sql_segment().replace(SINGLE_QUOTE, EMPTY);
+        let val = like_text(val).chars().fold(String::new(), |mut escaped, c| {
+            if c == LIKE_ESCAPE || c == '%' || c == '_' {
+                escaped.push(LIKE_ESCAPE);
+            }
+            escaped.push(c);
+            escaped
+        });
         match *self {
-            SqlLike::DEFAULT => Segment::Extenssion(format!("'%{}%'", val)),
-            SqlLike::LEFT => Segment::Extenssion(format!("'%{}'", val)),
-            SqlLike::RIGHT => Segment::Extenssion(format!("'{}%'", val)),
+            SqlLike::DEFAULT => Segment::Quoted(format!("%{}%", val)),
+            SqlLike::LEFT => Segment::Quoted(format!("%{}", val)),
+            SqlLike::RIGHT => Segment::Quoted(format!("{}%", val)),
         }
     }
+
+    /// Whether the pattern of the value has escaped characters, MySQL and SQLite share no default escape character.
+    pub fn needs_escape(val: &Segment) -> bool {
+        like_text(val.to_owned()).chars().any(|c| c == LIKE_ESCAPE || c == '%' || c == '_')
+    }
+}
+
+/// the escape character of the like patterns, unlike `\` it needs no escaping in a MySQL string literal
+const LIKE_ESCAPE: char = '!';
+
+fn like_text(val: Segment) -> String {
+    match val {
+        Segment::Quoted(val) => val,
+        mut val => val.get_sql_segment().replace(SINGLE_QUOTE, EMPTY),
+    }
 }
 
 impl ISegment for SqlKeyword {
@@ -1,22 +1,56 @@
 //! 
 //! Generate Wrapper.
-//! ```ignore
-//! 
-//! let mut wrapper = UpdateWrapper::new();
-//! wrapper.like(true, "column1", "ffff");
-//! wrapper.eq(true, "column2", 12);
-//! wrapper.eq(true, "column3", "3333");
-//! wrapper.in_(true, "column4", vec![1,44,3]);
-//! wrapper.not_between(true, "column5", 2, 8);
-//! wrapper.set(true, "column1", 4);
-//! match wrapper.get_target_sql("t_user") {
+//!
+//! Every builder takes the wrapper by value and hands it back, so conditions chain in one expression.
+//! The `*_condition` variants take a leading `bool` and skip the condition when it's false.
+//! ```rust
+//! use akita::Wrapper;
+//!
+//! let mut wrapper = Wrapper::new()
+//!     .like("column1", "ffff")
+//!     .eq("column2", 12)
+//!     .eq_condition(false, "column3", "3333")
+//!     .inside("column4", vec![1, 44, 3])
+//!     .not_between("column5", 2, 8)
+//!     .set("column1", 4);
+//! match wrapper.get_update_sql("t_user") {
 //!     Ok(sql) => {println!("ok:{}", sql);}
 //!     Err(err) => {println!("err:{}", err);}
 //! }
 //! ```
+//! A wrapper built in steps is rebound at each one.
+//! ```rust
+//! use akita::{ISegment, Wrapper};
 //!
+//! let name = Some("akita");
+//! let mut wrapper = Wrapper::new().eq("status", 1);
+//! if let Some(name) = name {
+//!     wrapper = wrapper.eq("name", name);
+//! }
+//! assert_eq!(wrapper.get_sql_segment().trim(), "(status = 1 and name = 'akita')");
+//! ```
 //!
-use crate::{segment::{MergeSegments, Segment, SqlKeyword, SqlLike, ToSegment, ISegment}, comm::*};
+use chrono::NaiveDateTime;
+
+use crate::{segment::{MergeSegments, Segment, SqlKeyword, SqlLike, ToSegment, ISegment}, comm::*, DatabasePlatform, Params, Value};
+
+/// The kind of a `Wrapper::join`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+}
+
+impl JoinKind {
+    fn keyword(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "INNER JOIN",
+            JoinKind::Left => "LEFT JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Wrapper{
@@ -34,6 +68,16 @@ pub struct Wrapper{
     pub sql_first: Option<String>,
     /// SQL结束语句
     pub last_sql: Option<String>,
+    /// JOIN 语句
+    pub joins: Vec<String>,
+    /// DISTINCT 查询
+    pub distinct: bool,
+    /// DISTINCT ON 字段
+    pub distinct_on: Vec<String>,
+    /// 最大行数
+    pub limit: Option<usize>,
+    /// 跳过行数
+    pub offset: Option<usize>,
     pub expression: MergeSegments,
 }
 
@@ -57,7 +101,7 @@ impl ISegment for Wrapper {
 impl Wrapper{
 
     pub fn new() -> Self {
-        Self { sql_set: Vec::new(), expression: MergeSegments::default(), param_name_seq: 0, sql_first: None, last_sql: None, sql_comment: None, sql_select: None, fields_set: Vec::new() }
+        Self { sql_set: Vec::new(), expression: MergeSegments::default(), param_name_seq: 0, sql_first: None, last_sql: None, sql_comment: None, sql_select: None, fields_set: Vec::new(), joins: Vec::new(), distinct: false, distinct_on: Vec::new(), limit: None, offset: None }
     }
 
     pub fn set<S: Into<String>, U: ToSegment>(self, column: S, val: U) -> Self {
@@ -73,6 +117,18 @@ impl Wrapper{
         self
     }
 
+    /// Set a column to the value of another column, e.g. `` `backup_name`=`name` ``, both names are escaped as identifiers.
+    pub fn set_column<S: Into<String>, U: Into<String>>(self, target: S, source: U) -> Self {
+        self.set_column_condition(true, target, source)
+    }
+
+    pub fn set_column_condition<S: Into<String>, U: Into<String>>(mut self, condition: bool, target: S, source: U) -> Self {
+        if condition {
+            self.sql_set.push(escape_identifier(&target.into()) + EQUALS + escape_identifier(&source.into()).as_str());
+        }
+        self
+    }
+
     pub fn set_sql<S: Into<String>>(mut self, sql: S) -> Self {
         let sql: String = sql.into();
         if !sql.is_empty() {
@@ -95,6 +151,38 @@ impl Wrapper{
         self.sql_set.clear();
     }
 
+    /// The shape of the query without any of its values, e.g. `select * | where status = ? and name like ? | order by id desc`,
+    /// stable across wrappers differing only in their values, for a query allowlist or an audit log.
+    /// Raw sql fragments such as `apply` or `last` keep their text with the literals replaced by `?`.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("select {}{}", if self.distinct { "distinct " } else { "" }, self.sql_select.as_deref().map(normalize_sql).unwrap_or_else(|| "*".to_string()))];
+        if !self.joins.is_empty() {
+            parts.push(self.joins.iter().map(|join| normalize_sql(join)).collect::<Vec<_>>().join(SPACE));
+        }
+        if !self.distinct_on.is_empty() {
+            parts.push(format!("distinct on {}", self.distinct_on.join(COMMA)));
+        }
+        if !self.sql_set.is_empty() {
+            parts.push(format!("set {}", self.sql_set.iter().map(|set| normalize_sql(set)).collect::<Vec<_>>().join(COMMA)));
+        }
+        let clauses = [("where", &self.expression.normal), ("group by", &self.expression.group_by), ("having", &self.expression.having), ("order by", &self.expression.order_by)];
+        for (clause, list) in clauses.iter() {
+            if !list.segments.is_empty() {
+                parts.push(format!("{} {}", clause, describe_segments(&list.segments)));
+            }
+        }
+        if let Some(last) = self.last_sql.as_deref().filter(|last| !last.trim().is_empty()) {
+            parts.push(format!("last {}", normalize_sql(last)));
+        }
+        parts.join(" | ")
+    }
+
+    /// Empty the where conditions only, the select, group by, having, order by and the `last` sql such as a limit are kept,
+    /// e.g. to run several filters over the same projection and ordering.
+    pub fn clear_conditions(&mut self) {
+        self.expression.clear_normal();
+    }
+
     pub fn get_update_sql(&mut self, table_name: &str) -> Result<String, &str> {
         let set_fields = if let Some(set) = self.get_set_sql() {
             set.to_owned()
@@ -114,6 +202,120 @@ impl Wrapper{
         }
     }
 
+    /// `SELECT DISTINCT` the selected columns. Without a `select` it's `DISTINCT *`, which dedups whole rows and so
+    /// only has an effect on tables without a primary key, select the columns to dedup by instead.
+    /// `count` and the total of `page` count the distinct rows, as `COUNT(DISTINCT col)` for a single plain column.
+    pub fn distinct(mut self) -> Self { self.distinct = true; self }
+    /// Keep the first row of each group of the columns only, like `DISTINCT ON (columns)` of Postgres,
+    /// where the first row is decided by the `asc_by`/`desc_by` ordering, e.g. the latest event per user.
+    /// MySQL and SQLite have no `DISTINCT ON`, so it's emulated with a derived table numbering the rows by
+    /// `ROW_NUMBER() OVER (PARTITION BY columns ORDER BY ...)`, which needs MySQL 8.0 or SQLite 3.25.
+    pub fn distinct_on(mut self, columns: Vec<&str>) -> Self { self.distinct_on = columns.iter().filter(|col| !col.trim().is_empty()).map(|col| col.trim().to_string()).collect(); self }
+    /// `kind JOIN table ON on` after the table of the select, e.g. `join(JoinKind::Left, "t_order o", "o.user_id = t_user.id")`.
+    /// Several joins are rendered in call order, so an `on` may refer to any table joined before it.
+    /// The table and the `on` are written as is, qualify the columns of `select` and of the conditions (`o.amount`)
+    /// since the columns of the entity are selected unqualified by default.
+    ///
+    /// # Panics
+    /// Panics on a blank table or `on`.
+    pub fn join<S: Into<String>, O: Into<String>>(self, kind: JoinKind, table: S, on: O) -> Self { self.join_condition(true, kind, table, on) }
+    pub fn join_condition<S: Into<String>, O: Into<String>>(mut self, condition: bool, kind: JoinKind, table: S, on: O) -> Self {
+        if condition {
+            let (table, on) = (table.into(), on.into());
+            assert!(!table.trim().is_empty() && !on.trim().is_empty(), "join needs a table and an on clause");
+            self.joins.push(format!("{} {} ON {}", kind.keyword(), table.trim(), on.trim()));
+        }
+        self
+    }
+    /// An inline table of the rows for `join`, e.g. `from_values(rows, "m", &["id", "name"])` gives
+    /// `(SELECT 1 AS id, 'a' AS name UNION ALL SELECT 2, 'b') m`. The `VALUES` table constructor differs between
+    /// MySQL (`VALUES ROW(..)` with `column_0`.. names) and SQLite (`column1`..) and neither takes the `AS m(id, name)`
+    /// column list, so it's written as a union of selects understood by both. The values are inlined, not bound.
+    ///
+    /// # Panics
+    /// Panics on no rows, a row whose length differs from the columns, or an alias or column that is not a plain identifier.
+    pub fn from_values(rows: Vec<Vec<Value>>, alias: &str, cols: &[&str]) -> String {
+        assert!(is_identifier(alias) && !cols.is_empty() && cols.iter().all(|col| is_identifier(col)), "invalid values table {}({})", alias, cols.join(COMMA));
+        assert!(!rows.is_empty() && rows.iter().all(|row| row.len() == cols.len()), "every row of the values table {} needs {} values", alias, cols.len());
+        let selects = rows.iter().enumerate().map(|(y, row)| {
+            let values = row.iter().zip(cols.iter()).map(|(value, col)| {
+                let value = if value.is_nil() { "NULL".to_string() } else { value.to_segment().get_sql_segment() };
+                // the first select names the columns
+                if y == 0 { format!("{} AS {}", value, col) } else { value }
+            }).collect::<Vec<_>>().join(", ");
+            format!("SELECT {}", values)
+        }).collect::<Vec<_>>().join(" UNION ALL ");
+        format!("({}) {}", selects, alias)
+    }
+
+    /// The conditions like `get_sql_segment` with the values bound as parameters rather than written into the sql,
+    /// e.g. `(name = ? and age in (?,?))` with the three values in order. MySQL takes `?`, SQLite numbered `$n` placeholders.
+    /// Every mapper method taking a wrapper binds it this way. Raw sql such as `apply`, `exists` or `in_sql` is kept as written,
+    /// so are the `set` values.
+    pub fn build_parameterized(&mut self, platform: &DatabasePlatform) -> (String, Params) {
+        let (mut bound, params) = self.bind_params(platform);
+        (bound.get_sql_segment(), params)
+    }
+
+    /// a copy of the wrapper with its values replaced by the placeholders of the platform, and the values in order
+    pub(crate) fn bind_params(&self, platform: &DatabasePlatform) -> (Wrapper, Params) {
+        let (bound, values) = self.bind_params_after(platform, 0);
+        (bound, if values.is_empty() { Params::Nil } else { Params::Vector(values) })
+    }
+
+    /// like `bind_params` for a statement binding `first` values ahead of the conditions, e.g. the set values of an update,
+    /// the numbered placeholders go on from `$first + 1`
+    pub(crate) fn bind_params_after(&self, _platform: &DatabasePlatform, first: usize) -> (Wrapper, Vec<Value>) {
+        #[allow(unreachable_patterns)]
+        match _platform {
+            #[cfg(feature = "akita-sqlite")]
+            DatabasePlatform::Sqlite(_) => self.without_row_values().bind_values_after(true, first),
+            _ => self.bind_values_after(false, first),
+        }
+    }
+
+    /// a copy of the wrapper with the row-value IN of `in_tuple` expanded into OR'd AND groups, for SQLite
+    #[cfg_attr(not(feature = "akita-sqlite"), allow(dead_code))]
+    fn without_row_values(&self) -> Wrapper {
+        let mut expanded = self.to_owned();
+        expanded.expression.normal.segments.iter_mut().for_each(expand_row_values);
+        expanded
+    }
+
+    #[cfg(test)]
+    fn bind_values(&self, numbered: bool) -> (Wrapper, Vec<Value>) {
+        self.bind_values_after(numbered, 0)
+    }
+
+    fn bind_values_after(&self, numbered: bool, first: usize) -> (Wrapper, Vec<Value>) {
+        let mut bound = self.to_owned();
+        let mut values = Vec::new();
+        // with distinct_on the ordering is rendered twice, around the conditions, so it keeps its values inline
+        let order_by = if bound.distinct_on.is_empty() { bound.expression.order_by.segments.as_mut_slice() } else { &mut [] };
+        bound.expression.normal.segments.iter_mut().chain(bound.expression.having.segments.iter_mut()).chain(order_by.iter_mut())
+            .for_each(|segment| bind_segment(segment, &mut values, numbered, first));
+        (bound, values)
+    }
+
+    /// The source after `FROM` and the `WHERE ...` rest of a select on the table. With `distinct_on` the source is
+    /// the derived table of the first rows per group, the conditions are applied within it before the rows are numbered.
+    /// The joins follow the table, with `distinct_on` the table is aliased `akita_src` within the derived table.
+    pub fn get_from_sql(&mut self, table: &str) -> (String, String) {
+        let joins = self.joins.iter().map(|join| format!(" {}", join)).collect::<String>();
+        if self.distinct_on.is_empty() {
+            let where_condition = self.get_sql_segment();
+            let where_condition = if where_condition.trim().is_empty() { String::default() } else { format!("WHERE {}", where_condition) };
+            return (format!("{}{}", table, joins), where_condition);
+        }
+        let partition = self.distinct_on.iter().map(|col| quote_column(col)).collect::<Vec<_>>().join(COMMA);
+        let order_by = self.expression.order_by.get_sql_segment();
+        let condition = self.expression.normal.get_sql_segment();
+        let inner_where = if condition.trim().is_empty() { String::default() } else { format!(" WHERE {}", condition) };
+        let from = format!("(SELECT akita_src.*, ROW_NUMBER() OVER (PARTITION BY {}{}) AS akita_rn FROM {} akita_src{}{}) akita_distinct", partition, order_by, table, joins, inner_where);
+        let where_condition = format!("WHERE akita_rn = 1{}{}{} {}", self.expression.group_by.get_sql_segment(), self.expression.having_sql(), order_by, self.last_sql.to_owned().unwrap_or_default());
+        (from, where_condition.trim_end().to_string())
+    }
+
     pub fn get_query_sql(mut self, table_name: &str) -> Result<String, &str> {
         let select_fields = self.get_select_sql();
         if table_name.is_empty() {
@@ -121,6 +323,7 @@ impl Wrapper{
         } else {
             let condition = self.expression.get_sql_segment();
             let mut sql = format!("select {} from {}", select_fields, table_name);
+            self.joins.iter().for_each(|join| { sql.push(' '); sql.push_str(join); });
             if !condition.is_empty() {
                 sql.push_str("where ");
                 sql.push_str(&condition);
@@ -137,12 +340,35 @@ impl Wrapper{
     pub fn ge<S: Into<String>, U: ToSegment>(self, column: S, val: U) -> Self { self.add_condition(true, Segment::ColumnField(column.into()), SqlKeyword::GE, val.into()) }
     pub fn lt<S: Into<String>, U: ToSegment>(self, column: S, val: U) -> Self { self.add_condition(true, Segment::ColumnField(column.into()), SqlKeyword::LT, val.into()) }
     pub fn le<S: Into<String>, U: ToSegment>(self, column: S, val: U) -> Self { self.add_condition(true, Segment::ColumnField(column.into()), SqlKeyword::LE, val.into()) }
+    /// `CAST(column AS type) = val`, for legacy columns storing numbers as text and the like.
+    /// The cast type is one of the logical types `signed`/`int`, `unsigned`, `decimal`, `char`/`string`, `date`, `datetime` or `time`,
+    /// mapped to names both MySQL and SQLite understand.
+    ///
+    /// # Panics
+    /// Panics on a cast type outside of the list above.
+    pub fn cast_eq<S: Into<String>, U: ToSegment>(self, column: S, cast_type: &str, val: U) -> Self {
+        let sql_type = cast_sql_type(cast_type).unwrap_or_else(|| panic!("unsupported cast type: {}", cast_type));
+        self.add_condition(true, Segment::Extenssion(format!("CAST({} AS {})", column.into(), sql_type)), SqlKeyword::EQ, val.into())
+    }
     pub fn eq_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.add_condition(condition, Segment::ColumnField(column.into()), SqlKeyword::EQ, val.into()) }
     pub fn ne_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.add_condition(condition, Segment::ColumnField(column.into()), SqlKeyword::NE, val.into()) }
     pub fn gt_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.add_condition(condition, Segment::ColumnField(column.into()), SqlKeyword::GT, val.into()) }
     pub fn ge_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.add_condition(condition, Segment::ColumnField(column.into()), SqlKeyword::GE, val.into()) }
     pub fn lt_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.add_condition(condition, Segment::ColumnField(column.into()), SqlKeyword::LT, val.into()) }
     pub fn le_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.add_condition(condition, Segment::ColumnField(column.into()), SqlKeyword::LE, val.into()) }
+    /// Cap the rows of a `list` without paginating, the limit is placed after the `order by`, as
+    /// `limit offset, n` on MySQL and `limit n offset offset` on SQLite. `page` ignores it in favour of its own limit,
+    /// don't combine it with a limit written through `last` either.
+    pub fn limit(mut self, n: usize) -> Self { self.limit = n.into(); self }
+    /// Skip the first rows of a `list`, without a `limit` all the remaining rows are returned.
+    pub fn offset(mut self, n: usize) -> Self { self.offset = n.into(); self }
+    /// the row limiting fragment of `limit` and `offset`, empty when neither is set
+    pub(crate) fn limit_sql(&self, platform: &DatabasePlatform) -> String {
+        if self.limit.is_none() && self.offset.is_none() {
+            return String::default()
+        }
+        platform.limit_clause(self.offset.unwrap_or_default(), self.limit.unwrap_or(i64::MAX as usize))
+    }
     pub fn first<S: Into<String>>(self, sql: S) -> Self { self.first_condition(true, sql) }
     pub fn last<S: Into<String>>(self, sql: S) -> Self { self.last_condition(true, sql) }
     pub fn first_condition<S: Into<String>>(mut self, condition: bool, sql: S) -> Self { if condition { self.sql_first = format!("{}{}", sql.into(), SPACE ).into(); } self }
@@ -151,6 +377,27 @@ impl Wrapper{
     pub fn not_in<S: Into<String>, U: ToSegment + Clone>(self, column: S, vals: Vec<U>) -> Self { self.not().inside(column, vals) }
     pub fn not_in_condition<S: Into<String>, U: ToSegment + Clone>(self, condition: bool, column: S, vals: Vec<U>) -> Self { self.not_condition(condition).in_condition(condition, column, vals) }
     pub fn in_condition<S: Into<String>, U: ToSegment + Clone>(mut self, condition: bool, column: S, vals: Vec<U>) -> Self { let segs: Vec<Segment> = vals.iter().map(|val|val.to_owned().into()).collect::<Vec<Segment>>(); if condition { self.append_sql_segments(vec![Segment::ColumnField(column.into()), SqlKeyword::IN.into(), Self::in_expression(segs)]) }; self }
+    /// Row-value IN over several columns, e.g. `(tenant_id, user_id) in ((1, 10), (1, 11))` for composite keys.
+    /// The values are bound like the other values, see `build_parameterized`. SQLite accepts no row-value list on
+    /// the right of IN, so when bound for SQLite the condition is expanded into OR'd AND groups instead.
+    /// An empty list matches nothing.
+    ///
+    /// # Panics
+    ///
+    /// Panics when there are no columns or a row's arity differs from the column count.
+    pub fn in_tuple<S: Into<String>>(self, columns: Vec<S>, rows: Vec<Vec<Value>>) -> Self {
+        let columns = columns.into_iter().map(Into::into).collect::<Vec<String>>();
+        assert!(!columns.is_empty(), "in_tuple needs at least one column");
+        for row in rows.iter() {
+            assert_eq!(row.len(), columns.len(), "in_tuple row {:?} doesn't match the columns {:?}", row, columns);
+        }
+        if rows.is_empty() {
+            return self.apply("1 = 0")
+        }
+        let columns = columns.iter().map(|col| Segment::ColumnField(quote_column(col))).collect::<Vec<_>>();
+        let rows = rows.iter().map(|row| Segment::List(row.iter().map(|value| if value.is_nil() { Segment::Str("NULL") } else { value.to_segment() }).collect())).collect::<Vec<_>>();
+        self.do_it(true, vec![SqlKeyword::APPLY.into(), Segment::Group(vec![Segment::List(columns), SqlKeyword::IN.into(), Segment::List(rows)])])
+    }
     pub fn append_sql_segments(&mut self, sql_segments: Vec<Segment>) { self.expression.add(sql_segments); }
     pub fn do_it(mut self, condition: bool, segments: Vec<Segment>) -> Self {
         if condition {
@@ -160,8 +407,35 @@ impl Wrapper{
     }
     pub fn comment<S: Into<String>>(self, comment: S) -> Self { self.comment_condition(true, comment) }
     pub fn comment_condition<S: Into<String>>(mut self, condition: bool, comment: S) -> Self { if condition { self.sql_comment = comment.into().into(); } self }
-    pub fn get_select_sql(&mut self) -> String { if let Some(select) = &self.sql_select { select.to_owned() } else { "*".to_string() } }
-    pub fn select(mut self, columns: Vec<String>) -> Self { if !columns.is_empty() { self.sql_select = columns.join(",").into(); } self }
+    pub fn get_select_sql(&mut self) -> String {
+        let select = if let Some(select) = &self.sql_select { select.to_owned() } else { "*".to_string() };
+        if self.distinct { format!("DISTINCT {}", select) } else { select }
+    }
+    /// The sql counting the rows of a select on `from`, the distinct rows when the wrapper is `distinct`.
+    pub(crate) fn get_count_sql(&mut self, from: &str, where_condition: &str) -> String {
+        match &self.sql_select {
+            Some(select) if self.distinct && !select.contains(',') && !select.to_uppercase().contains(" AS ") => {
+                format!("select count(distinct {}) as count from {} {}", select, from, where_condition)
+            }
+            _ if self.distinct => format!("select count(1) as count from (select {} from {} {}) akita_count", self.get_select_sql(), from, where_condition),
+            _ => format!("select count(1) as count from {} {}", from, where_condition),
+        }
+    }
+    /// Select the columns, plain and qualified names are quoted part by part (`t.col` gives `` `t`.`col` ``).
+    /// Anything else such as a function call or an alias is kept as written, use `select_raw` to skip the quoting entirely.
+    /// The columns are appended to those of earlier `select`/`select_as`/`select_raw` calls, in call order.
+    pub fn select(self, columns: Vec<String>) -> Self { let columns = columns.iter().map(|col| quote_column(col)).collect::<Vec<_>>().join(","); self.select_raw(columns) }
+    /// Append `expr AS alias` to the select, the alias may then be used by `group_by`, `having` and `asc_by`/`desc_by`,
+    /// and it is the column name a `FromValue` struct reads, e.g. `select_as("id", "user_id")` fills `user_id`.
+    /// A plain column is quoted like in `select`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the alias is not a plain identifier. Aliases are written as is rather than quoted like the
+    /// `select` columns, so they stay usable in `order by` where the ordering columns are not quoted either.
+    pub fn select_as<S: Into<String>, A: Into<String>>(self, expr: S, alias: A) -> Self { let alias = alias.into(); assert!(is_identifier(&alias), "invalid select alias: {}", alias); self.select_raw(format!("{} AS {}", quote_column(&expr.into()), alias)) }
+    /// Append a select expression as written, e.g. `count(*) as total` or an already quoted column, after the `select` columns.
+    pub fn select_raw<S: Into<String>>(mut self, expr: S) -> Self { let expr = expr.into(); if !expr.trim().is_empty() { self.sql_select = match self.sql_select { Some(select) => format!("{},{}", select, expr), None => expr }.into(); } self }
     pub fn like<S: Into<String>, U: ToSegment>(self, column: S, val: U) -> Self { self.like_value(true, Segment::ColumnField(column.into()), SqlLike::DEFAULT, val.into()) }
     pub fn like_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.like_value(condition, Segment::ColumnField(column.into()), SqlLike::DEFAULT, val.into()) }
     pub fn not_like<S: Into<String>, U: ToSegment>(self, column: S, val: U) -> Self { self.not().like_value(true, Segment::ColumnField(column.into()), SqlLike::DEFAULT, val.into()) }
@@ -170,39 +444,79 @@ impl Wrapper{
     pub fn like_left_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.like_value(condition, Segment::ColumnField(column.into()), SqlLike::LEFT, val.into()) }
     pub fn like_right<S: Into<String>, U: ToSegment>(self, column: S, val: U) -> Self { self.like_value(true, Segment::ColumnField(column.into()), SqlLike::RIGHT, val.into()) }
     pub fn like_right_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val: U) -> Self { self.like_value(condition, Segment::ColumnField(column.into()), SqlLike::RIGHT, val.into()) }
-    pub fn in_expression(mut vals: Vec<Segment>) -> Segment { 
+    pub fn in_expression(vals: Vec<Segment>) -> Segment { 
         if vals.is_empty() { 
             Segment::Str("()") 
         } 
         else {  
-            Segment::Text(LEFT_BRACKET.to_string() + vals.iter_mut().map(|val| val.get_sql_segment()).collect::<Vec<String>>().join(COMMA).as_str() + RIGHT_BRACKET) 
+            Segment::List(vals) 
         } 
     }
-    pub fn between<S: Into<String>, U: ToSegment>(self, column: S, val1: U, val2: U) -> Self { self.do_it(true, vec![column.into().into(), SqlKeyword::BETWEEN.into(), val1.into(), SqlKeyword::AND.into(), val2.into() ]) }
-    pub fn between_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val1: U, val2: U) -> Self { self.do_it(condition, vec![column.into().into(), SqlKeyword::BETWEEN.into(), val1.into(), SqlKeyword::AND.into(), val2.into() ]) }
+    pub fn between<S: Into<String>, U: ToSegment>(self, column: S, val1: U, val2: U) -> Self { self.do_it(true, vec![Segment::ColumnField(column.into()), SqlKeyword::BETWEEN.into(), val1.into(), SqlKeyword::AND.into(), val2.into() ]) }
+    pub fn between_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val1: U, val2: U) -> Self { self.do_it(condition, vec![Segment::ColumnField(column.into()), SqlKeyword::BETWEEN.into(), val1.into(), SqlKeyword::AND.into(), val2.into() ]) }
+    /// Inclusive datetime range over `between`, both bounds render as `'%Y-%m-%d %H:%M:%S'` which MySQL and SQLite both compare correctly.
+    /// Sub-second precision is dropped from the bounds.
+    pub fn between_dates<S: Into<String>>(self, column: S, start: NaiveDateTime, end: NaiveDateTime) -> Self { self.between(column, start, end) }
+    pub fn between_dates_condition<S: Into<String>>(self, condition: bool, column: S, start: NaiveDateTime, end: NaiveDateTime) -> Self { self.between_condition(condition, column, start, end) }
     pub fn not_between<S: Into<String>, U: ToSegment>(self, column: S, val1: U, val2: U) -> Self { self.not().between(column, val1, val2) }
     pub fn not_between_condition<S: Into<String>, U: ToSegment>(self, condition: bool, column: S, val1: U, val2: U) -> Self { self.not_condition(condition).between_condition(condition, column, val1, val2) }
     pub fn add_condition(self, condition: bool, column: Segment, sql_keword: SqlKeyword, val: Segment) -> Self { 
         self.do_it(condition, vec![column, sql_keword.into(), val]) 
     }
-    pub fn like_value(self, condition: bool, column: Segment, sql_like: SqlLike, val: Segment) -> Self { self.do_it(condition, vec![column, SqlKeyword::LIKE.into(), sql_like.concat_like(val)]) }
+    /// `column like pattern` of the value, `%` and `_` within the value match literally, use `apply` for a raw pattern.
+    pub fn like_value(self, condition: bool, column: Segment, sql_like: SqlLike, val: Segment) -> Self {
+        let escape = SqlLike::needs_escape(&val);
+        let mut segments = vec![column, SqlKeyword::LIKE.into(), sql_like.concat_like(val)];
+        if escape {
+            segments.push(Segment::Str("escape '!'"));
+        }
+        self.do_it(condition, segments)
+    }
+    /// Negate the next single condition only, e.g. `not().like("name", "x")` gives `not (name like '%x%')`.
+    /// The modifier resets after that condition, it doesn't chain across several ones, use `not` on a nested group for that.
     pub fn not(self) -> Self { self.do_it(true, vec![ SqlKeyword::NOT.into() ]) }
-    pub fn and<F: FnOnce(Self) -> Self>(self, f: F) -> Self {
-        self.and_inner().add_nested_condition(true, f)
+    /// Apply the closure only when the condition holds, for blocks of optional filters.
+    ///
+    /// ```rust
+    /// use akita::Wrapper;
+    /// let (name, min_age, gender): (Option<&str>, Option<i32>, Option<i32>) = (Some("akita"), None, Some(1));
+    /// let wrapper = Wrapper::new()
+    ///     .eq("status", 1)
+    ///     .when(name.is_some(), |w| w.like("name", name.unwrap_or_default()))
+    ///     .when(min_age.is_some(), |w| w.ge("age", min_age.unwrap_or_default()))
+    ///     .when(gender.is_some(), |w| w.eq("gender", gender.unwrap_or_default()).desc_by(vec!["id"]));
+    /// ```
+    pub fn when<F: FnOnce(Self) -> Self>(self, condition: bool, f: F) -> Self { if condition { f(self) } else { self } }
+    pub fn and<F: FnOnce(Self) -> Self>(self, f: F) -> Self { self.and_with(f) }
+    pub fn or<F: FnOnce(Self) -> Self>(self, f: F) -> Self { self.or_with(f) }
+    /// Join the conditions built by the closure as one parenthesized group with AND, groups nest freely.
+    pub fn and_with<F: FnOnce(Self) -> Self>(self, f: F) -> Self {
+        let instance = f(self.nested());
+        self.attach_and(instance)
     }
-    pub fn or<F: FnOnce(Self) -> Self>(self, f: F) -> Self {
-        self.or_inner().add_nested_condition(true, f)
+    /// Join the conditions built by the closure as one parenthesized group with OR,
+    /// e.g. `eq("a", 1).eq("b", 2).or_with(|w| w.eq("c", 3))` gives `(a = 1 and b = 2 or (c = 3))`.
+    /// A closure that adds no condition leaves the wrapper unchanged rather than emitting `()`.
+    pub fn or_with<F: FnOnce(Self) -> Self>(self, f: F) -> Self {
+        let instance = f(self.nested());
+        self.attach_or(instance)
     }
 
-    fn add_nested_condition<F: FnOnce(Self) -> Self>(self, condition: bool, f: F) -> Self {
-        if condition {
-            let instance = f(Self::new());
-            //let mut segs = instance.expression.get_normal();
-            // segs.insert(0, SqlKeyword::BRACKET.into());
-            self.do_it(true, vec![SqlKeyword::BRACKET.into(), instance.into()])
-        } else {
-            self
-        }
+    /// Create an empty child wrapper, it can be passed around and filled independently,
+    /// then attached back with `attach_and` or `attach_or`.
+    /// The child's conditions are rendered at the attach point, in the order they were added.
+    pub fn nested(&self) -> Self {
+        let mut instance = Self::new();
+        instance.param_name_seq = self.param_name_seq;
+        instance
+    }
+
+    pub fn attach_and(self, nested: Self) -> Self { if nested.expression.normal.segments.is_empty() { self } else { self.and_inner().attach_nested(nested) } }
+    pub fn attach_or(self, nested: Self) -> Self { if nested.expression.normal.segments.is_empty() { self } else { self.or_inner().attach_nested(nested) } }
+
+    fn attach_nested(mut self, nested: Self) -> Self {
+        self.param_name_seq = self.param_name_seq.max(nested.param_name_seq);
+        self.do_it(true, vec![SqlKeyword::BRACKET.into(), nested.into()])
     }
 
     fn and_inner(self) -> Self { self.do_it(true, vec![SqlKeyword::AND.into()]) }
@@ -214,22 +528,82 @@ impl Wrapper{
     pub fn or_direct(self) -> Self { self.do_it(true, vec![SqlKeyword::OR.into()]) }
     pub fn apply<S: Into<String>>(self, apply_sql: S) -> Self { self.do_it(true, vec![SqlKeyword::APPLY.into(), Segment::Extenssion(apply_sql.into())]) }
     pub fn apply_condition<S: Into<String>>(self, condition: bool, apply_sql: S) -> Self { self.do_it(condition, vec![SqlKeyword::APPLY.into(), Segment::Extenssion(apply_sql.into())]) }
-    pub fn is_null<S: Into<String>>(self, column: S) -> Self { self.do_it(true, vec![ column.into().into(), SqlKeyword::IS_NULL.into() ]) }
-    pub fn is_null_condition<S: Into<String>>(self, condition: bool, column: S) -> Self { self.do_it(condition, vec![ column.into().into(), SqlKeyword::IS_NULL.into() ]) }
-    pub fn is_not_null<S: Into<String>>(self, column: S) -> Self { self.do_it(true, vec![ column.into().into(), SqlKeyword::IS_NOT_NULL.into() ]) }
-    pub fn is_not_null_condition<S: Into<String>>(self, condition: bool, column: S) -> Self { self.do_it(condition, vec![ column.into().into(), SqlKeyword::IS_NOT_NULL.into() ]) }
+    /// Like `apply` with `?` placeholders for the values, e.g. `apply_params("DATE(created_at) = ?", vec![day.to_value()])`.
+    /// The values are quoted like any other condition value, or bound when the wrapper is executed.
+    ///
+    /// # Panics
+    /// Panics when the number of `?` outside quoted literals differs from the number of values.
+    pub fn apply_params<S: Into<String>>(self, apply_sql: S, params: Vec<Value>) -> Self { self.apply_params_condition(true, apply_sql, params) }
+    pub fn apply_params_condition<S: Into<String>>(self, condition: bool, apply_sql: S, params: Vec<Value>) -> Self {
+        if !condition {
+            return self;
+        }
+        let segments = sub_segments(&apply_sql.into(), params);
+        self.do_it(true, vec![SqlKeyword::APPLY.into(), Segment::Group(segments)])
+    }
+    /// RAW: append a constant boolean expression without parameters, e.g. `deleted = 0`, joined to the other conditions with `and`.
+    /// The sql is used as is, never pass user input here. A blank sql adds nothing.
+    pub fn raw_condition<S: Into<String>>(self, sql: S) -> Self { let sql = sql.into(); let condition = !sql.trim().is_empty(); self.do_it(condition, vec![SqlKeyword::APPLY.into(), Segment::Extenssion(format!("({})", sql.trim()))]) }
+    /// `` `col` is null `` without binding a value, `eq(col, Value::Nil)` gives `col = NULL` which never matches.
+    pub fn is_null<S: Into<String>>(self, column: S) -> Self { self.do_it(true, vec![ Segment::ColumnField(quote_column(&column.into())), SqlKeyword::IS_NULL.into() ]) }
+    pub fn is_null_condition<S: Into<String>>(self, condition: bool, column: S) -> Self { self.do_it(condition, vec![ Segment::ColumnField(quote_column(&column.into())), SqlKeyword::IS_NULL.into() ]) }
+    pub fn is_not_null<S: Into<String>>(self, column: S) -> Self { self.do_it(true, vec![ Segment::ColumnField(quote_column(&column.into())), SqlKeyword::IS_NOT_NULL.into() ]) }
+    pub fn is_not_null_condition<S: Into<String>>(self, condition: bool, column: S) -> Self { self.do_it(condition, vec![ Segment::ColumnField(quote_column(&column.into())), SqlKeyword::IS_NOT_NULL.into() ]) }
     pub fn not_exists<S: Into<String>>(self, not_exists_sql: S) -> Self  { self.not().exists(not_exists_sql) }
     pub fn not_exists_condition<S: Into<String>>(self, condition: bool, not_exists_sql: S) -> Self  { self.not_condition(condition).exists_condition(condition, not_exists_sql) }
+    /// `EXISTS (sub_sql)` with the `?` placeholders of the sub query filled in order by the params, bound in place
+    /// along with the other values of the wrapper when it's executed. The params are any `ToValue`, or `Value`s for mixed types.
+    /// Correlated references such as `o.user_id = t_user.id` are written raw in the sub query by the caller.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the number of params doesn't match the placeholders.
+    pub fn exists_sub<S: Into<String>, U: Into<Value>>(self, sub_sql: S, params: Vec<U>) -> Self { let segments = sub_segments(&sub_sql.into(), params.into_iter().map(Into::into).collect()); self.do_it(true, vec![SqlKeyword::EXISTS.into(), Segment::Group(segments)]) }
+    /// `NOT EXISTS (sub_sql)`, see `exists_sub`.
+    pub fn not_exists_sub<S: Into<String>, U: Into<Value>>(self, sub_sql: S, params: Vec<U>) -> Self { self.not().exists_sub(sub_sql, params) }
     pub fn exists<S: Into<String>>(self, exists_sql: S) -> Self { self.do_it(true, vec![SqlKeyword::EXISTS.into(), Segment::Extenssion(format!("({})", exists_sql.into()))]) }
     pub fn exists_condition<S: Into<String>>(self, condition: bool, exists_sql: S) -> Self { self.do_it(condition, vec![SqlKeyword::EXISTS.into(), Segment::Extenssion(format!("({})", exists_sql.into()))]) }
     pub fn in_sql<S: Into<String>, U: Into<String>>(self, column: S, in_val: U) -> Self { self.do_it(true, vec![column.into().into() ,SqlKeyword::IN.into(), Segment::Extenssion(format!("({})", in_val.into()))]) }
     pub fn in_sql_condition<S: Into<String>, U: Into<String>>(self, condition: bool, column: S, in_val: U) -> Self { self.do_it(condition, vec![column.into().into() ,SqlKeyword::IN.into(), Segment::Extenssion(format!("({})", in_val.into()))]) }
+    /// `column IN (SELECT <sub select> FROM table WHERE <sub conditions>)`, the sub wrapper's values are rendered in place, ahead of any later condition.
+    pub fn in_wrapper<S: Into<String>, T: Into<String>>(self, column: S, table: T, sub: Wrapper) -> Self { self.in_subquery(column, table, sub) }
+    /// `column in (SELECT ... FROM table WHERE ...)` of the sub wrapper's select and conditions, `*` when it selects nothing.
+    /// The sub query's values are bound in place along with the outer ones, see `build_parameterized`.
+    pub fn in_subquery<S: Into<String>, T: Into<String>>(self, column: S, table: T, sub: Wrapper) -> Self { self.in_subquery_condition(true, column, table, sub) }
+    pub fn in_subquery_condition<S: Into<String>, T: Into<String>>(mut self, condition: bool, column: S, table: T, mut sub: Wrapper) -> Self {
+        self.param_name_seq = self.param_name_seq.max(sub.param_name_seq);
+        let select = format!("SELECT {} FROM {} WHERE", sub.get_select_sql(), table.into());
+        self.do_it(condition, vec![Segment::ColumnField(column.into()), SqlKeyword::IN.into(), Segment::Group(vec![Segment::Extenssion(select), Segment::Wrapper(Box::new(sub))])])
+    }
+    pub fn not_in_subquery<S: Into<String>, T: Into<String>>(self, column: S, table: T, sub: Wrapper) -> Self { self.not().in_subquery(column, table, sub) }
+    pub fn not_in_subquery_condition<S: Into<String>, T: Into<String>>(self, condition: bool, column: S, table: T, sub: Wrapper) -> Self { self.not_condition(condition).in_subquery_condition(condition, column, table, sub) }
     pub fn group_by<S: Into<String> + Clone>(self, columns: Vec<S>) -> Self { let cols: Vec<String> = columns.iter().map(|col|col.to_owned().into()).collect::<Vec<String>>();if columns.is_empty() { self } else { self.do_it(true, vec![SqlKeyword::GROUP_BY.into(), Segment::ColumnField(cols.join(COMMA))]) } }
     pub fn group_by_condition<S: Into<String> + Clone>(self, condition: bool, columns: Vec<S>) -> Self { let cols: Vec<String> = columns.iter().map(|col|col.to_owned().into()).collect::<Vec<String>>();if columns.is_empty() { self } else { self.do_it(condition, vec![SqlKeyword::GROUP_BY.into(), Segment::ColumnField(cols.join(COMMA))]) } }
-    pub fn having<S: Into<String>>(self, sql_having: S) -> Self { self.do_it(true, vec![SqlKeyword::HAVING.into(), sql_having.into().into()]) }
-    pub fn having_condition<S: Into<String>>(self, condition: bool, sql_having: S) -> Self { self.do_it(condition, vec![SqlKeyword::HAVING.into(), sql_having.into().into()]) }
+    /// A raw aggregate predicate such as `count(*) > 5`, several ones are joined with `and`.
+    /// The having clause is only emitted along with a `group_by`.
+    pub fn having<S: Into<String>>(self, sql_having: S) -> Self { self.having_condition(true, sql_having) }
+    pub fn having_condition<S: Into<String>>(self, condition: bool, sql_having: S) -> Self { let sql_having = sql_having.into(); let condition = condition && !sql_having.trim().is_empty(); self.do_it(condition, vec![SqlKeyword::HAVING.into(), Segment::Extenssion(sql_having)]) }
+    /// Order by the columns or select aliases (see `select_as`), both are written as is without quoting.
+    /// Each call appends to the ordering, e.g. `asc_by(vec!["a"]).desc_by(vec!["b"])` gives `order by a asc , b desc`,
+    /// which `list` and `page` place after the conditions and before the limit.
     pub fn order_by<S: Into<String> + Clone>(self, is_asc: bool, columns: Vec<S>) -> Self { let cols: Vec<String> = columns.iter().map(|col|col.to_owned().into()).collect::<Vec<String>>();if columns.is_empty() { self } else { let mode = if is_asc { SqlKeyword::ASC } else { SqlKeyword::DESC }; self.do_it(true, vec![ SqlKeyword::ORDER_BY.into(), Segment::ColumnField(cols.join(COMMA)), mode.into() ]) } }
     pub fn asc_by<S: Into<String> + Clone>(self, columns: Vec<S>) -> Self { self.order_by(true, columns) }
+    /// Order by a rank given to each value of the column, e.g. `urgent` before `normal`, rendered as
+    /// `CASE col WHEN 'urgent' THEN 1 WHEN 'normal' THEN 2 ELSE 3 END asc`. The values not listed come last.
+    /// The values and ranks are bound like the other values, see `build_parameterized`, unless the wrapper uses `distinct_on`.
+    pub fn order_by_case<S: Into<String>>(self, column: S, order: Vec<(Value, i32)>) -> Self {
+        let order = order.into_iter().filter(|(value, _)| !value.is_nil()).collect::<Vec<_>>();
+        if order.is_empty() {
+            return self
+        }
+        let last = order.iter().map(|(_, rank)| *rank).max().unwrap_or_default().saturating_add(1);
+        let mut segments = vec![SqlKeyword::ORDER_BY.into(), Segment::Extenssion(format!("CASE {}", quote_column(&column.into())))];
+        for (value, rank) in order.iter() {
+            segments.extend(vec![Segment::Str("WHEN"), value.to_segment(), Segment::Str("THEN"), Segment::Int32(*rank)]);
+        }
+        segments.extend(vec![Segment::Str("ELSE"), Segment::Int32(last), Segment::Str("END"), SqlKeyword::ASC.into()]);
+        self.do_it(true, segments)
+    }
     pub fn desc_by<S: Into<String> + Clone>(self, columns: Vec<S>) -> Self { self.order_by(false, columns) }
     pub fn order_by_condition<S: Into<String> + Clone>(self, condition: bool, is_asc: bool, columns: Vec<S>) -> Self { let cols: Vec<String> = columns.iter().map(|col|col.to_owned().into()).collect::<Vec<String>>();if columns.is_empty() { self } else { let mode = if is_asc { SqlKeyword::ASC } else { SqlKeyword::DESC }; self.do_it(condition, vec![ SqlKeyword::ORDER_BY.into(), Segment::ColumnField(cols.join(COMMA)), mode.into() ]) } }
     pub fn asc_by_condition<S: Into<String> + Clone>(self, condition: bool, columns: Vec<S>) -> Self { self.order_by_condition(condition, true, columns) }
@@ -237,6 +611,147 @@ impl Wrapper{
 }
 
 
+/// split the sql on the `?` placeholders outside quotes into raw parts and the value segments in order,
+/// panics when the number of placeholders differs from the number of values
+fn sub_segments(sql: &str, params: Vec<Value>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut part = String::new();
+    let mut params = params.into_iter();
+    let mut quote: Option<char> = None;
+    for c in sql.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' || c == '`' => quote = Some(c),
+            None if c == '?' => {
+                let value = params.next().unwrap_or_else(|| panic!("too few params for `{}`", sql));
+                if !part.trim().is_empty() {
+                    segments.push(Segment::Extenssion(part.trim().to_string()));
+                }
+                part.clear();
+                segments.push(if value.is_nil() { Segment::Str("NULL") } else { value.to_segment() });
+                continue;
+            }
+            None => {}
+        }
+        part.push(c);
+    }
+    assert!(params.next().is_none(), "too many params for `{}`", sql);
+    if !part.trim().is_empty() {
+        segments.push(Segment::Extenssion(part.trim().to_string()));
+    }
+    segments
+}
+
+/// expand the `(a, b) in ((1, 2), ...)` group of `in_tuple` into `((a = 1 and b = 2) or ...)`, brackets and nested wrappers are walked
+#[cfg_attr(not(feature = "akita-sqlite"), allow(dead_code))]
+fn expand_row_values(segment: &mut Segment) {
+    match segment {
+        Segment::Group(segments) => {
+            if let [Segment::List(columns), Segment::Keyword(SqlKeyword::IN), Segment::List(rows)] = segments.as_slice() {
+                if columns.iter().all(|col| matches!(col, Segment::ColumnField(_))) && rows.iter().all(|row| matches!(row, Segment::List(_))) {
+                    let mut groups = Vec::with_capacity(rows.len() * 2);
+                    for row in rows.iter() {
+                        if let Segment::List(values) = row {
+                            let mut group = Vec::with_capacity(columns.len() * 4);
+                            for (col, value) in columns.iter().zip(values.iter()) {
+                                if !group.is_empty() {
+                                    group.push(SqlKeyword::AND.into());
+                                }
+                                group.extend(vec![col.to_owned(), SqlKeyword::EQ.into(), value.to_owned()]);
+                            }
+                            if !groups.is_empty() {
+                                groups.push(SqlKeyword::OR.into());
+                            }
+                            groups.push(Segment::Group(group));
+                        }
+                    }
+                    *segment = Segment::Group(groups);
+                    return
+                }
+            }
+            segments.iter_mut().for_each(expand_row_values)
+        }
+        Segment::Wrapper(wrapper) => wrapper.expression.normal.segments.iter_mut().for_each(expand_row_values),
+        _ => {}
+    }
+}
+
+/// replace a value segment by its placeholder and collect the value, brackets and nested wrappers are walked in render order
+fn bind_segment(segment: &mut Segment, values: &mut Vec<Value>, numbered: bool, first: usize) {
+    let value = match segment {
+        Segment::Quoted(v) => Value::Text(v.to_owned()),
+        Segment::Int8(v) => Value::Tinyint(*v),
+        Segment::Int16(v) => Value::Smallint(*v),
+        Segment::Int32(v) => Value::Int(*v),
+        Segment::Int64(v) => Value::Bigint(*v),
+        Segment::U8(v) => Value::Smallint(*v as i16),
+        Segment::U16(v) => Value::Int(*v as i32),
+        Segment::U32(v) => Value::Bigint(*v as i64),
+        Segment::U64(v) => Value::Bigint(*v as i64),
+        Segment::Usize(v) => Value::Bigint(*v as i64),
+        Segment::Isize(v) => Value::Bigint(*v as i64),
+        Segment::Float(v) => Value::Double(*v),
+        Segment::Boolean(v) => Value::Bool(*v),
+        Segment::DateTime(v) => Value::DateTime(*v),
+        Segment::Date(v) => Value::Date(*v),
+        Segment::Group(segments) | Segment::List(segments) => return segments.iter_mut().for_each(|segment| bind_segment(segment, values, numbered, first)),
+        Segment::Wrapper(wrapper) => return wrapper.expression.normal.segments.iter_mut().chain(wrapper.expression.having.segments.iter_mut()).for_each(|segment| bind_segment(segment, values, numbered, first)),
+        _ => return,
+    };
+    values.push(value);
+    *segment = Segment::Extenssion(if numbered { format!("${}", first + values.len()) } else { "?".to_string() });
+}
+
+/// the segments with every value replaced by `?`
+fn describe_segments(segments: &[Segment]) -> String {
+    segments.iter().filter_map(|segment| match segment {
+        Segment::Nil => None,
+        Segment::Keyword(keyword) => Some(keyword.to_owned().get_sql_segment()),
+        Segment::ColumnField(column) => Some(column.to_owned()),
+        Segment::Str(text) => Some(text.to_string()),
+        Segment::Extenssion(sql) => Some(normalize_sql(sql)),
+        Segment::Wrapper(wrapper) => Some(format!("({})", describe_segments(&wrapper.expression.normal.segments))),
+        Segment::Group(segments) => Some(format!("({})", describe_segments(segments))),
+        _ => Some("?".to_string()),
+    }).filter(|part| !part.is_empty()).collect::<Vec<_>>().join(SPACE)
+}
+
+/// quote the name as a single identifier, doubling the backticks within
+fn escape_identifier(name: &str) -> String {
+    format!("{}{}{}", BACKTICK, name.replace(BACKTICK, "``"), BACKTICK)
+}
+
+/// whether the name is a plain identifier such as a column or an alias
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// quote a plain or qualified column name part by part, anything else is returned as written
+fn quote_column(column: &str) -> String {
+    let column = column.trim();
+    let parts = column.split('.').collect::<Vec<_>>();
+    let quotable = parts.iter().enumerate().all(|(i, part)| is_identifier(part) || (i > 0 && i == parts.len() - 1 && *part == "*"));
+    if !quotable {
+        return column.to_string();
+    }
+    parts.iter().map(|part| if *part == "*" { part.to_string() } else { format!("{}{}{}", BACKTICK, part, BACKTICK) }).collect::<Vec<_>>().join(".")
+}
+
+/// the allowed logical cast types and their sql names
+fn cast_sql_type(cast_type: &str) -> Option<&'static str> {
+    match cast_type.to_lowercase().as_str() {
+        "signed" | "int" | "integer" => Some("SIGNED"),
+        "unsigned" => Some("UNSIGNED"),
+        "decimal" | "numeric" => Some("DECIMAL"),
+        "char" | "string" | "text" => Some("CHAR"),
+        "date" => Some("DATE"),
+        "datetime" => Some("DATETIME"),
+        "time" => Some("TIME"),
+        _ => None,
+    }
+}
+
 #[test]
 #[allow(unused)]
 fn basic_test() {
@@ -245,4 +760,227 @@ fn basic_test() {
     let mut wrapper = Wrapper::new().set_sql("a='b'").eq("a", "bn").last("limit 1");
         //.not_in("vecs", vec!["a","f","g"]);
     println!("{}", wrapper.get_set_sql().unwrap_or_default());
-}
\ No newline at end of file
+}
+#[test]
+fn nested_test() {
+    let mut wrapper = Wrapper::new().eq("a", 1);
+    let sub = wrapper.nested().eq("b", 2).or_direct().eq("c", "d");
+    wrapper = wrapper.attach_and(sub);
+    let empty = wrapper.nested();
+    wrapper = wrapper.attach_or(empty);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(a = 1 and  (b = 2 or c = 'd') )");
+}
+#[test]
+fn or_with_test() {
+    let mut wrapper = Wrapper::new().eq("a", 1).eq("b", 2).or_with(|w| w.eq("c", 3));
+    assert_eq!(wrapper.get_sql_segment().trim(), "(a = 1 and b = 2 or  (c = 3) )");
+    let mut wrapper = Wrapper::new().and_with(|w| w.eq("a", 1).or_with(|w| w.eq("b", 2).and_with(|w| w))).or_with(|w| w).eq("c", 3);
+    assert_eq!(wrapper.get_sql_segment().trim(), "( (a = 1 or  (b = 2) )  and c = 3)");
+}
+#[test]
+fn between_dates_test() {
+    use chrono::NaiveDate;
+    let start = NaiveDate::from_ymd_opt(2021, 8, 1).and_then(|d| d.and_hms_opt(0, 0, 0)).unwrap();
+    let end = NaiveDate::from_ymd_opt(2021, 8, 31).and_then(|d| d.and_hms_milli_opt(23, 59, 59, 500)).unwrap();
+    let mut wrapper = Wrapper::new().between_dates("created_at", start, end);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(created_at between '2021-08-01 00:00:00' and '2021-08-31 23:59:59')");
+}
+#[test]
+fn is_null_test() {
+    let mut wrapper = Wrapper::new().is_null("deleted_at");
+    assert_eq!(wrapper.get_sql_segment().trim(), "(`deleted_at` is null)");
+    let mut wrapper = Wrapper::new().is_null_condition(false, "a").eq("b", 1).is_not_null_condition(true, "u.email");
+    assert_eq!(wrapper.get_sql_segment().trim(), "(b = 1 and `u`.`email` is not null)");
+}
+#[test]
+fn bind_values_test() {
+    let wrapper = Wrapper::new().eq("name", "it's").inside("age", vec![18, 20]).between("score", 1.5, 9.0).not().like("code", "x").or_with(|w| w.gt("id", 3u8));
+    let (mut bound, values) = wrapper.bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "(name = ? and age in (?,?) and score between ? and ? and not (code like ?) or  (id > ?) )");
+    assert_eq!(values, vec![Value::Text("it's".to_string()), Value::Int(18), Value::Int(20), Value::Double(1.5), Value::Double(9.0), Value::Text("%x%".to_string()), Value::Smallint(3)]);
+    let (mut bound, _) = Wrapper::new().eq("a", 1).eq("b", 2).bind_values(true);
+    assert_eq!(bound.get_sql_segment().trim(), "(a = $1 and b = $2)");
+    let (mut bound, values) = Wrapper::new().eq("a", 1).inside("b", vec![2, 3]).bind_values_after(true, 2);
+    assert_eq!(bound.get_sql_segment().trim(), "(a = $3 and b in ($4,$5))");
+    assert_eq!(values, vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+}
+#[test]
+fn compare_range_test() {
+    let mut wrapper = Wrapper::new().ge("age", 18).lt("age", 65).gt_condition(false, "age", 0).le_condition(true, "score", 9.5);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(age >= 18 and age < 65 and score <= 9.5)");
+    let (mut bound, values) = Wrapper::new().ge("age", 18).lt("age", 65).bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "(age >= ? and age < ?)");
+    assert_eq!(values, vec![Value::Int(18), Value::Int(65)]);
+}
+#[test]
+fn apply_params_test() {
+    let mut wrapper = Wrapper::new().eq("a", 1).apply("DATE(created_at) = CURDATE()").apply_params("DATE(updated_at) = ? or code = ?", vec![Value::Text("2024-01-01".to_string()), Value::Int(7)]);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(a = 1 and DATE(created_at) = CURDATE() and (DATE(updated_at) = '2024-01-01' or code = 7))");
+    let (mut bound, values) = Wrapper::new().apply_params_condition(false, "b = ?", vec![]).apply_params("c > ?", vec![Value::Int(2)]).bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "((c > ?))");
+    assert_eq!(values, vec![Value::Int(2)]);
+    let (mut bound, values) = Wrapper::new().apply_params("note <> '?' and code = ?", vec![Value::Int(7)]).bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "((note <> '?' and code = ?))");
+    assert_eq!(values, vec![Value::Int(7)]);
+}
+#[test]
+fn in_wrapper_test() {
+    let sub = Wrapper::new().select(vec!["user_id".to_string()]).eq("status", 1);
+    let mut wrapper = Wrapper::new().eq("a", 1).in_wrapper("id", "t_order", sub).eq("b", "c");
+    assert_eq!(wrapper.get_sql_segment().trim(), "(a = 1 and id in (SELECT `user_id` FROM t_order WHERE (status = 1)) and b = 'c')");
+}
+#[test]
+fn in_subquery_test() {
+    let sub = Wrapper::new().select(vec!["user_id".to_string()]).eq("status", 1).like("note", "x");
+    let wrapper = Wrapper::new().eq("a", 2).not_in_subquery("id", "t_order", sub).eq("b", "c");
+    let (mut bound, values) = wrapper.bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "(a = ? and not (id in (SELECT `user_id` FROM t_order WHERE (status = ? and note like ?))) and b = ?)");
+    assert_eq!(values, vec![Value::Int(2), Value::Int(1), Value::Text("%x%".to_string()), Value::Text("c".to_string())]);
+}
+#[test]
+fn cast_eq_test() {
+    let mut wrapper = Wrapper::new().cast_eq("code", "unsigned", 12);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(CAST(code AS UNSIGNED) = 12)");
+    assert_eq!(cast_sql_type("varchar(10)); drop table t; --"), None);
+}
+#[test]
+fn raw_condition_test() {
+    let mut wrapper = Wrapper::new().raw_condition("deleted = 0").eq("a", 1).raw_condition(" ").raw_condition("1 = 1");
+    assert_eq!(wrapper.get_sql_segment().trim(), "((deleted = 0) and a = 1 and (1 = 1))");
+}
+#[test]
+fn not_modifier_test() {
+    let mut wrapper = Wrapper::new().not().like("name", "x").eq("a", 1);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(not (name like '%x%') and a = 1)");
+    let mut wrapper = Wrapper::new().eq("a", 1).not().eq("b", 2).ne("c", 3).not_in("d", vec![4, 5]);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(a = 1 and not (b = 2) and c <> 3 and not (d in (4,5)))");
+}
+#[test]
+fn select_raw_test() {
+    let mut wrapper = Wrapper::new().select(vec!["id".to_string(), "u.name".to_string(), "o.*".to_string(), "max(price)".to_string()]).select_raw("count(*) as total");
+    assert_eq!(wrapper.get_select_sql(), "`id`,`u`.`name`,`o`.*,max(price),count(*) as total");
+    assert_eq!(Wrapper::new().select_raw("t.col").get_select_sql(), "t.col");
+}
+#[test]
+fn order_by_test() {
+    let mut wrapper = Wrapper::new().eq("a", 1).asc_by(vec!["a", "c"]).desc_by(vec!["b"]).asc_by(Vec::<&str>::new());
+    assert_eq!(wrapper.get_sql_segment().trim(), "(a = 1) order by a,c asc , b desc");
+    assert_eq!(Wrapper::new().eq("a", 1).get_sql_segment().trim(), "(a = 1)");
+}
+#[test]
+fn group_by_having_test() {
+    let mut wrapper = Wrapper::new().eq("status", 1).group_by(vec!["user_id", "day"]).having("count(*) > 5").having_condition(false, "sum(x) < 1").having("sum(amount) < 300").desc_by(vec!["user_id"]);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(status = 1) group by user_id,day having count(*) > 5 and sum(amount) < 300 order by user_id desc");
+    assert_eq!(Wrapper::new().eq("a", 1).having("count(*) > 5").get_sql_segment().trim(), "(a = 1)");
+    assert_eq!(Wrapper::new().group_by(vec!["a"]).having("count(*) > 5").get_sql_segment().trim(), "(1 = 1) group by a having count(*) > 5");
+}
+#[test]
+fn order_by_case_test() {
+    let wrapper = Wrapper::new().eq("a", 1).order_by_case("status", vec![(Value::Text("urgent".to_string()), 1), (Value::Nil, 2), (Value::Text("normal".to_string()), 2)]).desc_by(vec!["id"]);
+    assert_eq!(wrapper.to_owned().get_sql_segment().trim(), "(a = 1) order by CASE `status` WHEN 'urgent' THEN 1 WHEN 'normal' THEN 2 ELSE 3 END asc , id desc");
+    let (mut bound, values) = wrapper.bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "(a = ?) order by CASE `status` WHEN ? THEN ? WHEN ? THEN ? ELSE ? END asc , id desc");
+    assert_eq!(values, vec![Value::Int(1), Value::Text("urgent".to_string()), Value::Int(1), Value::Text("normal".to_string()), Value::Int(2), Value::Int(3)]);
+}
+#[test]
+fn like_escape_test() {
+    let mut wrapper = Wrapper::new().like_right("code", "10%_off").not_like("name", "a!b").like_left("tag", "x");
+    assert_eq!(wrapper.get_sql_segment().trim(), "(code like '10!%!_off%' escape '!' and not (name like '%a!!b%' escape '!') and tag like '%x')");
+}
+#[test]
+fn order_by_alias_test() {
+    let mut wrapper = Wrapper::new().select(vec!["user_id".to_string()]).select_as("count(*)", "cnt").eq("status", 1).group_by(vec!["user_id"]).desc_by(vec!["cnt"]);
+    assert_eq!(wrapper.get_select_sql(), "`user_id`,count(*) AS cnt");
+    assert_eq!(wrapper.get_sql_segment().trim(), "(status = 1) group by user_id order by cnt desc");
+}
+#[test]
+fn distinct_test() {
+    let mut wrapper = Wrapper::new().select(vec!["city".to_string()]).distinct().eq("status", 1);
+    assert_eq!(wrapper.get_select_sql(), "DISTINCT `city`");
+    assert_eq!(wrapper.get_count_sql("t_user", "WHERE (status = 1)"), "select count(distinct `city`) as count from t_user WHERE (status = 1)");
+    let mut wrapper = Wrapper::new().select(vec!["city".to_string(), "age".to_string()]).distinct();
+    assert_eq!(wrapper.get_count_sql("t_user", ""), "select count(1) as count from (select DISTINCT `city`,`age` from t_user ) akita_count");
+    let mut wrapper = Wrapper::new().distinct();
+    assert_eq!(wrapper.get_select_sql(), "DISTINCT *");
+    assert_eq!(Wrapper::new().get_count_sql("t_user", ""), "select count(1) as count from t_user ");
+}
+#[test]
+fn join_test() {
+    let mut wrapper = Wrapper::new().select(vec!["u.name".to_string(), "o.amount".to_string(), "p.title".to_string()])
+        .join(JoinKind::Inner, "t_order o", "o.user_id = u.id")
+        .join(JoinKind::Left, "t_product p", "p.id = o.product_id")
+        .join_condition(false, JoinKind::Right, "t_x x", "x.id = u.id")
+        .gt("o.amount", 10);
+    assert_eq!(wrapper.get_select_sql(), "`u`.`name`,`o`.`amount`,`p`.`title`");
+    let (from, where_condition) = wrapper.get_from_sql("t_user u");
+    assert_eq!(from, "t_user u INNER JOIN t_order o ON o.user_id = u.id LEFT JOIN t_product p ON p.id = o.product_id");
+    assert_eq!(where_condition.trim(), "WHERE  (o.amount > 10)");
+}
+#[test]
+fn from_values_test() {
+    let values = Wrapper::from_values(vec![vec![Value::Int(1), Value::Text("a".to_string())], vec![Value::Int(2), Value::Nil]], "m", &["id", "name"]);
+    assert_eq!(values, "(SELECT 1 AS id, 'a' AS name UNION ALL SELECT 2, NULL) m");
+    let (from, _) = Wrapper::new().join(JoinKind::Inner, values, "m.id = t.type_id").get_from_sql("t_user t");
+    assert_eq!(from, "t_user t INNER JOIN (SELECT 1 AS id, 'a' AS name UNION ALL SELECT 2, NULL) m ON m.id = t.type_id");
+}
+#[test]
+fn select_as_test() {
+    let mut wrapper = Wrapper::new().select_as("id", "user_id").select(vec!["name".to_string()]).select_as("u.email", "mail");
+    assert_eq!(wrapper.get_select_sql(), "`id` AS user_id,`name`,`u`.`email` AS mail");
+}
+#[test]
+fn distinct_on_test() {
+    let mut wrapper = Wrapper::new().eq("type", "login").desc_by(vec!["created_at"]).distinct_on(vec!["user_id"]);
+    let (from, where_condition) = wrapper.get_from_sql("events");
+    assert_eq!(from, "(SELECT akita_src.*, ROW_NUMBER() OVER (PARTITION BY `user_id` order by created_at desc) AS akita_rn FROM events akita_src WHERE (type = 'login')) akita_distinct");
+    assert_eq!(where_condition, "WHERE akita_rn = 1 order by created_at desc");
+}
+#[test]
+fn when_test() {
+    let params: Vec<Option<&str>> = vec![None];
+    let keyword = params[0];
+    let mut wrapper = Wrapper::new().eq("a", 1).when(keyword.is_some(), |w| w.like("name", keyword.unwrap_or_default())).when(true, |w| w.eq("b", 2).ne("c", 3));
+    assert_eq!(wrapper.get_sql_segment().trim(), "(a = 1 and b = 2 and c <> 3)");
+}
+#[test]
+fn in_tuple_test() {
+    let wrapper = Wrapper::new().eq("status", 1).in_tuple(vec!["tenant_id", "user_id"], vec![vec![Value::Int(1), Value::Int(10)], vec![Value::Int(1), Value::Text("11".to_string())]]);
+    assert_eq!(wrapper.clone().get_sql_segment().trim(), "(status = 1 and ((`tenant_id`,`user_id`) in ((1,10),(1,'11'))))");
+    let (mut bound, values) = wrapper.bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "(status = ? and ((`tenant_id`,`user_id`) in ((?,?),(?,?))))");
+    assert_eq!(values, vec![Value::Int(1), Value::Int(1), Value::Int(10), Value::Int(1), Value::Text("11".to_string())]);
+    let (mut bound, values) = wrapper.without_row_values().bind_values(true);
+    assert_eq!(bound.get_sql_segment().trim(), "(status = $1 and ((`tenant_id` = $2 and `user_id` = $3) or (`tenant_id` = $4 and `user_id` = $5)))");
+    assert_eq!(values.len(), 5);
+    let mut wrapper = Wrapper::new().in_tuple(vec!["tenant_id", "user_id"], vec![]);
+    assert_eq!(wrapper.get_sql_segment().trim(), "(1 = 0)");
+}
+#[test]
+fn clear_conditions_test() {
+    let mut wrapper = Wrapper::new().select(vec!["id".to_string(), "name".to_string()]).eq("status", 1).like("name", "ak").desc_by(vec!["id"]).last("limit 10");
+    wrapper.clear_conditions();
+    let mut wrapper = wrapper.eq("status", 2);
+    assert_eq!(wrapper.get_select_sql(), "`id`,`name`");
+    assert_eq!(wrapper.get_sql_segment().trim(), "(status = 2) order by id desc  limit 10");
+}
+#[test]
+fn set_column_test() {
+    let mut wrapper = Wrapper::new().set("status", 1).set_column("backup_name", "name").set_column_condition(false, "a", "b").set_column("odd`col", "name").eq("id", 1);
+    assert_eq!(wrapper.get_update_sql("t_user").unwrap(), "update t_user set status=1,`backup_name`=`name`,`odd``col`=`name` where (id = 1)");
+}
+#[test]
+fn describe_test() {
+    let shape = |status: i32, name: &str, ids: Vec<i32>, size: usize| Wrapper::new().eq("status", status).like("name", name).in_condition(true, "id", ids).desc_by(vec!["id"]).last(format!("limit {}", size)).describe();
+    assert_eq!(shape(1, "akita", vec![1, 2], 10), shape(2, "cat", vec![3, 4, 5], 20));
+    assert_eq!(shape(1, "akita", vec![1, 2], 10), "select * | where status = ? and name like ? and id in ? | order by id desc | last limit ?");
+    assert_ne!(shape(1, "akita", vec![1], 10), Wrapper::new().eq("status", 1).describe());
+}
+#[test]
+fn exists_sub_test() {
+    let wrapper = Wrapper::new().eq("status", 1).exists_sub("SELECT 1 FROM t_order o WHERE o.user_id = t_user.id AND o.state = ? AND o.remark <> '?' AND o.amount > ?", vec![Value::Text("pa'id".to_string()), Value::Int(100)]);
+    let (mut bound, values) = wrapper.bind_values(false);
+    assert_eq!(bound.get_sql_segment().trim(), "(status = ? and exists (SELECT 1 FROM t_order o WHERE o.user_id = t_user.id AND o.state = ? AND o.remark <> '?' AND o.amount > ?))");
+    assert_eq!(values, vec![Value::Int(1), Value::Text("pa'id".to_string()), Value::Int(100)]);
+    let mut wrapper = Wrapper::new().not_exists_sub("SELECT 1 FROM t_order o WHERE o.user_id = t_user.id", Vec::<i32>::new());
+    assert_eq!(wrapper.get_sql_segment().trim(), "(not (exists (SELECT 1 FROM t_order o WHERE o.user_id = t_user.id)))");
+}